@@ -1,14 +1,56 @@
 use num_bigint::BigInt;
+use num_rational::BigRational;
 
-#[derive(Debug, PartialEq)]
+use crate::span::Span;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct TopLevel(pub Vec<Expr>);
 
-#[derive(Debug, PartialEq)]
-pub enum Expr {
+#[derive(Clone, Debug)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExprKind {
     List(Vec<Expr>),
+    Vector(Vec<Expr>),
+    // Keys and values alternate, there is always an even number of items
+    Map(Vec<Expr>),
+    Set(Vec<Expr>),
     Quoted(Box<Expr>),
+    Quasiquoted(Box<Expr>),
+    Unquoted(Box<Expr>),
+    UnquoteSpliced(Box<Expr>),
     Ident(String),
+    Keyword(String),
     Int(BigInt),
+    Float(f64),
+    Ratio(BigRational),
     Str(String),
+    Char(char),
     Bool(bool),
 }
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+// Spans don't take part in comparisons: two expressions are equal if they have
+// the same shape, no matter where they were read from.
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+// Expressions that don't come from source text (tests, generated code) get a
+// default span.
+impl From<ExprKind> for Expr {
+    fn from(kind: ExprKind) -> Self {
+        Self::new(kind, Span::default())
+    }
+}
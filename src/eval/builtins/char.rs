@@ -0,0 +1,56 @@
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::eval::{
+    env::Env,
+    error::{Arity, EResult, Error},
+    value::Value,
+};
+
+// (char->integer char)
+pub(super) fn char_to_integer(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Int(BigInt::from(to_char(&args[0])? as u32)))
+}
+
+// (integer->char code)
+pub(super) fn integer_to_char(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    match &args[0] {
+        Value::Int(code) => code
+            .to_u32()
+            .and_then(char::from_u32)
+            .map(Value::Char)
+            .ok_or_else(|| Error::argument(format!("{code} is not a character code"))),
+        v => Err(Error::type_error("int", v)),
+    }
+}
+
+// (char-upcase char)
+// Characters whose upper case is several characters, like ß, are kept as is.
+pub(super) fn char_upcase(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    let c = to_char(&args[0])?;
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(upper), None) => Ok(Value::Char(upper)),
+        _ => Ok(Value::Char(c)),
+    }
+}
+
+// (char-alphabetic? char)
+pub(super) fn is_char_alphabetic(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Bool(to_char(&args[0])?.is_alphabetic()))
+}
+
+pub(super) fn to_char(value: &Value) -> Result<char, Error> {
+    match value {
+        Value::Char(c) => Ok(*c),
+        v => Err(Error::type_error("char", v)),
+    }
+}
@@ -0,0 +1,43 @@
+use crate::eval::{
+    env::Env,
+    error::{Arity, EResult, Error},
+    value::Value,
+};
+
+// These all print to stdout and return nothing.
+
+// (display value)
+// Prints the human form of value: strings without quotes.
+pub(super) fn display(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    print!("{}", args[0]);
+    Ok(Value::None)
+}
+
+// (write value)
+// Prints the readable form of value, see `Value::written`. Symbols made with
+// `string->symbol` from a name that isn't an identifier print bare, since the
+// reader has no syntax for them.
+pub(super) fn write(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    print!("{}", args[0].written());
+    Ok(Value::None)
+}
+
+// (newline)
+pub(super) fn newline(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 0);
+
+    println!();
+    Ok(Value::None)
+}
+
+// (print value ...)
+// Displays the values separated by spaces, then ends the line.
+pub(super) fn print(args: Vec<Value>, _: &mut Env) -> EResult {
+    let line: Vec<_> = args.iter().map(Value::to_string).collect();
+    println!("{}", line.join(" "));
+    Ok(Value::None)
+}
@@ -0,0 +1,140 @@
+use itertools::Itertools;
+
+use crate::eval::{
+    env::Env,
+    error::{Arity, EResult, Error},
+    evaluator::apply,
+    value::Value,
+};
+
+// (cons car cdr)
+pub(super) fn cons(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2);
+
+    let (car, cdr) = args.into_iter().next_tuple().unwrap();
+    Ok(Value::cons(car, cdr))
+}
+
+// (car pair)
+pub(super) fn car(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    match &args[0] {
+        Value::Pair(pair) => Ok(pair.car.clone()),
+        v => Err(Error::type_error("pair", v)),
+    }
+}
+
+// (cdr pair)
+pub(super) fn cdr(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    match &args[0] {
+        Value::Pair(pair) => Ok(pair.cdr.clone()),
+        v => Err(Error::type_error("pair", v)),
+    }
+}
+
+// (list item ...)
+pub(super) fn list(args: Vec<Value>, _: &mut Env) -> EResult {
+    Ok(Value::list(args))
+}
+
+// (null? value)
+pub(super) fn is_null(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Bool(matches!(args[0], Value::Nil)))
+}
+
+// (pair? value)
+pub(super) fn is_pair(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Bool(matches!(args[0], Value::Pair(_))))
+}
+
+// (length list)
+pub(super) fn length(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Int(list_items(&args[0])?.len().into()))
+}
+
+// (append list ... tail)
+// Every argument but the last has to be a proper list, the last one becomes
+// the tail of the result as is.
+pub(super) fn append(mut args: Vec<Value>, _: &mut Env) -> EResult {
+    let Some(tail) = args.pop() else {
+        return Ok(Value::Nil);
+    };
+
+    let mut items = Vec::new();
+    for list in &args {
+        items.extend(list_items(list)?);
+    }
+    Ok(items
+        .into_iter()
+        .rev()
+        .fold(tail, |list, item| Value::cons(item, list)))
+}
+
+// (reverse list)
+pub(super) fn reverse(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    let items = list_items(&args[0])?;
+    Ok(items.into_iter().fold(Value::Nil, |list, item| Value::cons(item, list)))
+}
+
+// (map fun list ...)
+// With several lists, `fun` gets one argument from each and mapping stops at
+// the end of the shortest one.
+pub(super) fn map(args: Vec<Value>, env: &mut Env) -> EResult {
+    assert_arg_count!(args, 2..);
+
+    let mut args = args.into_iter();
+    let fun = args.next().unwrap();
+    let lists = args.map(|list| list_items(&list)).collect::<Result<Vec<_>, _>>()?;
+    let len = lists.iter().map(Vec::len).min().unwrap();
+
+    let mut lists: Vec<_> = lists.into_iter().map(Vec::into_iter).collect();
+    let results = (0..len)
+        .map(|_| {
+            let args = lists.iter_mut().map(|list| list.next().unwrap()).collect();
+            apply(fun.clone(), args, env)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::list(results))
+}
+
+// (filter pred list)
+pub(super) fn filter(args: Vec<Value>, env: &mut Env) -> EResult {
+    assert_arg_count!(args, 2);
+
+    let (pred, list) = args.into_iter().next_tuple().unwrap();
+    let mut kept = Vec::new();
+    for item in list_items(&list)? {
+        if apply(pred.clone(), vec![item.clone()], env)?.is_truthy() {
+            kept.push(item);
+        }
+    }
+    Ok(Value::list(kept))
+}
+
+// (fold fun init list)
+// Calls `(fun item acc)` for every item from left to right.
+pub(super) fn fold(args: Vec<Value>, env: &mut Env) -> EResult {
+    assert_arg_count!(args, 3);
+
+    let (fun, init, list) = args.into_iter().next_tuple().unwrap();
+    list_items(&list)?
+        .into_iter()
+        .try_fold(init, |acc, item| apply(fun.clone(), vec![item, acc], env))
+}
+
+pub(super) fn list_items(value: &Value) -> Result<Vec<Value>, Error> {
+    value
+        .list_items()
+        .ok_or_else(|| Error::type_error("list", value))
+}
@@ -0,0 +1,140 @@
+use itertools::Itertools;
+
+use crate::eval::{
+    env::Env,
+    error::{Arity, EResult, Error},
+    evaluator::apply,
+    value::{Map, Value},
+};
+
+// Maps and sets are immutable: assoc, dissoc, merge and update return a new
+// collection. Looking up a missing key gives false, like other searches.
+
+// (get coll key [default])
+// The value of key in a map, key itself if it is in a set.
+pub(super) fn get(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2..=3);
+
+    let mut args = args.into_iter();
+    let (coll, key) = args.next_tuple().unwrap();
+    let default = args.next().unwrap_or(Value::Bool(false));
+    let found = match coll {
+        Value::Map(map) => map.get(&key).cloned(),
+        Value::Set(set) => set.contains(&key).then_some(key),
+        v => return Err(Error::type_error("map or set", &v)),
+    };
+    Ok(found.unwrap_or(default))
+}
+
+// (assoc map key value ...)
+pub(super) fn assoc(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 3..);
+
+    if args.len().is_multiple_of(2) {
+        return Err(Error::argument("every key needs a value"));
+    }
+    let mut args = args.into_iter();
+    let mut map = to_map(args.next().unwrap())?;
+    for (key, value) in args.tuples() {
+        map.insert(key, value);
+    }
+    Ok(Value::Map(map))
+}
+
+// (dissoc coll key ...)
+// Removes keys from a map, elements from a set.
+pub(super) fn dissoc(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1..);
+
+    let mut args = args.into_iter();
+    match args.next().unwrap() {
+        Value::Map(mut map) => {
+            for key in args {
+                map.remove(&key);
+            }
+            Ok(Value::Map(map))
+        }
+        Value::Set(mut set) => {
+            for key in args {
+                set.remove(&key);
+            }
+            Ok(Value::Set(set))
+        }
+        v => Err(Error::type_error("map or set", &v)),
+    }
+}
+
+// (keys map)
+pub(super) fn keys(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    let map = to_map(args.into_iter().next().unwrap())?;
+    Ok(Value::list(map.keys().cloned().collect_vec()))
+}
+
+// (vals map), in the same order as (keys map)
+pub(super) fn vals(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    let map = to_map(args.into_iter().next().unwrap())?;
+    Ok(Value::list(map.values().cloned().collect_vec()))
+}
+
+// (contains? coll key)
+pub(super) fn is_contains(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2);
+
+    match &args[0] {
+        Value::Map(map) => Ok(Value::Bool(map.contains_key(&args[1]))),
+        Value::Set(set) => Ok(Value::Bool(set.contains(&args[1]))),
+        v => Err(Error::type_error("map or set", v)),
+    }
+}
+
+// (merge map ...) or (merge set ...)
+// For maps, the value from the rightmost map wins.
+pub(super) fn merge(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1..);
+
+    let mut args = args.into_iter();
+    match args.next().unwrap() {
+        Value::Map(map) => args
+            .try_fold(map, |mut acc, value| {
+                acc.extend(to_map(value)?);
+                Ok(acc)
+            })
+            .map(Value::Map),
+        Value::Set(set) => args
+            .try_fold(set, |acc, value| match value {
+                Value::Set(set) => Ok(acc.union(set)),
+                v => Err(Error::type_error("set", &v)),
+            })
+            .map(Value::Set),
+        v => Err(Error::type_error("map or set", &v)),
+    }
+}
+
+// (update map key f arg ...)
+// Sets key to (f value arg ...), where value is false if key is missing.
+pub(super) fn update(args: Vec<Value>, env: &mut Env) -> EResult {
+    assert_arg_count!(args, 3..);
+
+    let mut args = args.into_iter();
+    let (map, key, fun) = args.next_tuple().unwrap();
+    let mut map = to_map(map)?;
+    let current = map.get(&key).cloned().unwrap_or(Value::Bool(false));
+    let value = apply(fun, std::iter::once(current).chain(args).collect(), env)?;
+    map.insert(key, value);
+    Ok(Value::Map(map))
+}
+
+// -------------------------------------------------------------------------- //
+// Helpers                                                                    //
+// -------------------------------------------------------------------------- //
+
+fn to_map(value: Value) -> Result<Map, Error> {
+    match value {
+        Value::Map(map) => Ok(map),
+        v => Err(Error::type_error("map", &v)),
+    }
+}
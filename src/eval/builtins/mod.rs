@@ -0,0 +1,341 @@
+use std::{cmp::Ordering, rc::Rc};
+
+use itertools::Itertools;
+
+use crate::{lexer::Lexer, parser::Parser, span::Spanned};
+
+use super::{
+    env::Env,
+    error::{Arity, EResult, Error},
+    evaluator::{self, eval_toplevel},
+    value::Value,
+};
+
+// Defined before the submodules so that they can use it
+macro_rules! assert_arg_count {
+    ($args:ident, $min:literal..) => {
+        assert_arg_count!($args, Arity::AtLeast($min))
+    };
+    ($args:ident, $min:literal..=$max:literal) => {
+        assert_arg_count!($args, Arity::Between($min, $max))
+    };
+    ($args:ident, $count:literal) => {
+        assert_arg_count!($args, Arity::Exactly($count))
+    };
+    ($args:ident, $arity:expr) => {
+        if !$arity.accepts($args.len()) {
+            return Err(Error::arity($arity, $args.len()));
+        }
+    };
+}
+
+mod char;
+mod io;
+mod list;
+mod map;
+mod number;
+mod string;
+mod vector;
+
+impl Default for Env {
+    #[rustfmt::skip]
+    fn default() -> Self {
+        Self::new(vec![
+            ("+".into(), Value::Fun(Rc::new(add))),
+            ("*".into(), Value::Fun(Rc::new(mul))),
+            ("=".into(), Value::Fun(Rc::new(eq))),
+            ("!=".into(), Value::Fun(Rc::new(ne))),
+            (">".into(), Value::Fun(Rc::new(gt))),
+            ("<".into(), Value::Fun(Rc::new(lt))),
+            (">=".into(), Value::Fun(Rc::new(ge))),
+            ("<=".into(), Value::Fun(Rc::new(le))),
+            ("eq?".into(), Value::Fun(Rc::new(is_eqv))),
+            ("eqv?".into(), Value::Fun(Rc::new(is_eqv))),
+            ("equal?".into(), Value::Fun(Rc::new(is_equal))),
+            ("def".into(), Value::Fun(Rc::new(def))),
+            ("load".into(), Value::Fun(Rc::new(load))),
+            ("macroexpand-1".into(), Value::Fun(Rc::new(macroexpand_1))),
+            ("macroexpand".into(), Value::Fun(Rc::new(macroexpand))),
+            // Output
+            ("display".into(), Value::Fun(Rc::new(io::display))),
+            ("write".into(), Value::Fun(Rc::new(io::write))),
+            ("newline".into(), Value::Fun(Rc::new(io::newline))),
+            ("print".into(), Value::Fun(Rc::new(io::print))),
+            // Numbers
+            ("-".into(), Value::Fun(Rc::new(number::subtract))),
+            ("/".into(), Value::Fun(Rc::new(number::divide))),
+            ("quotient".into(), Value::Fun(Rc::new(number::quotient))),
+            ("remainder".into(), Value::Fun(Rc::new(number::remainder))),
+            ("modulo".into(), Value::Fun(Rc::new(number::modulo))),
+            ("abs".into(), Value::Fun(Rc::new(number::abs))),
+            ("min".into(), Value::Fun(Rc::new(number::min))),
+            ("max".into(), Value::Fun(Rc::new(number::max))),
+            ("expt".into(), Value::Fun(Rc::new(number::expt))),
+            ("gcd".into(), Value::Fun(Rc::new(number::gcd))),
+            ("lcm".into(), Value::Fun(Rc::new(number::lcm))),
+            ("sqrt".into(), Value::Fun(Rc::new(number::sqrt))),
+            ("exact->inexact".into(), Value::Fun(Rc::new(number::exact_to_inexact))),
+            ("zero?".into(), Value::Fun(Rc::new(number::is_zero))),
+            ("even?".into(), Value::Fun(Rc::new(number::is_even))),
+            ("odd?".into(), Value::Fun(Rc::new(number::is_odd))),
+            // Characters and strings
+            ("char->integer".into(), Value::Fun(Rc::new(char::char_to_integer))),
+            ("integer->char".into(), Value::Fun(Rc::new(char::integer_to_char))),
+            ("char-upcase".into(), Value::Fun(Rc::new(char::char_upcase))),
+            ("char-alphabetic?".into(), Value::Fun(Rc::new(char::is_char_alphabetic))),
+            ("string-ref".into(), Value::Fun(Rc::new(string::string_ref))),
+            ("string->list".into(), Value::Fun(Rc::new(string::string_to_list))),
+            ("list->string".into(), Value::Fun(Rc::new(string::list_to_string))),
+            ("string-length".into(), Value::Fun(Rc::new(string::string_length))),
+            ("substring".into(), Value::Fun(Rc::new(string::substring))),
+            ("string-index".into(), Value::Fun(Rc::new(string::string_index))),
+            ("string-split".into(), Value::Fun(Rc::new(string::string_split))),
+            ("string-join".into(), Value::Fun(Rc::new(string::string_join))),
+            ("string-trim".into(), Value::Fun(Rc::new(string::string_trim))),
+            ("string-upcase".into(), Value::Fun(Rc::new(string::string_upcase))),
+            ("string-downcase".into(), Value::Fun(Rc::new(string::string_downcase))),
+            ("string-contains?".into(), Value::Fun(Rc::new(string::is_string_contains))),
+            ("string-replace".into(), Value::Fun(Rc::new(string::string_replace))),
+            ("string->number".into(), Value::Fun(Rc::new(string::string_to_number))),
+            ("number->string".into(), Value::Fun(Rc::new(string::number_to_string))),
+            ("string->symbol".into(), Value::Fun(Rc::new(string::string_to_symbol))),
+            ("symbol->string".into(), Value::Fun(Rc::new(string::symbol_to_string))),
+            // Lists
+            ("cons".into(), Value::Fun(Rc::new(list::cons))),
+            ("car".into(), Value::Fun(Rc::new(list::car))),
+            ("cdr".into(), Value::Fun(Rc::new(list::cdr))),
+            ("list".into(), Value::Fun(Rc::new(list::list))),
+            ("null?".into(), Value::Fun(Rc::new(list::is_null))),
+            ("pair?".into(), Value::Fun(Rc::new(list::is_pair))),
+            ("length".into(), Value::Fun(Rc::new(list::length))),
+            ("append".into(), Value::Fun(Rc::new(list::append))),
+            ("reverse".into(), Value::Fun(Rc::new(list::reverse))),
+            ("map".into(), Value::Fun(Rc::new(list::map))),
+            ("filter".into(), Value::Fun(Rc::new(list::filter))),
+            ("fold".into(), Value::Fun(Rc::new(list::fold))),
+            // Vectors
+            ("vector-ref".into(), Value::Fun(Rc::new(vector::vector_ref))),
+            ("vector-set!".into(), Value::Fun(Rc::new(vector::vector_set))),
+            ("vector-length".into(), Value::Fun(Rc::new(vector::vector_length))),
+            ("vector->list".into(), Value::Fun(Rc::new(vector::vector_to_list))),
+            ("make-vector".into(), Value::Fun(Rc::new(vector::make_vector))),
+            ("subvector".into(), Value::Fun(Rc::new(vector::subvector))),
+            // Maps and sets
+            ("get".into(), Value::Fun(Rc::new(map::get))),
+            ("assoc".into(), Value::Fun(Rc::new(map::assoc))),
+            ("dissoc".into(), Value::Fun(Rc::new(map::dissoc))),
+            ("keys".into(), Value::Fun(Rc::new(map::keys))),
+            ("vals".into(), Value::Fun(Rc::new(map::vals))),
+            ("contains?".into(), Value::Fun(Rc::new(map::is_contains))),
+            ("merge".into(), Value::Fun(Rc::new(map::merge))),
+            ("update".into(), Value::Fun(Rc::new(map::update))),
+        ].into_iter().collect())
+    }
+}
+
+impl Env {
+    // The default environment plus `argc` and `(argv n)`, giving a script
+    // access to its command line. `(argv 0)` is the path of the script itself.
+    pub fn with_args(args: Vec<String>) -> Self {
+        let mut env = Self::default();
+        env.add_binding("argc".into(), Value::Int(args.len().into()));
+        env.add_binding("argv".into(), Value::Fun(Rc::new(move |a, _| argv(&args, a))));
+        env
+    }
+}
+
+pub(super) fn add(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2..);
+
+    use Value::*;
+    match args[0] {
+        Int(_) | Float(_) | Ratio(_) => {
+            let mut args = args.into_iter();
+            let first = args.next().unwrap();
+            args.try_fold(first, number::add)
+        }
+        Str(_) => args
+            .into_iter()
+            .map(|v| if let Str(v) = v { Ok(v) } else { Err(Error::type_error("string", &v)) })
+            .collect::<Result<_, _>>()
+            .map(Str),
+        ref v => Err(Error::type_error("number or string", v)),
+    }
+}
+
+pub(super) fn mul(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2..);
+
+    use Value::*;
+    match args[0] {
+        Int(_) | Float(_) | Ratio(_) => {
+            let mut args = args.into_iter();
+            let first = args.next().unwrap();
+            args.try_fold(first, number::mul)
+        }
+        Str(_) => {
+            assert_arg_count!(args, 2);
+            match args.into_iter().next_tuple().unwrap() {
+                (Value::Str(str), Value::Int(int)) => {
+                    let int: usize = int
+                        .try_into()
+                        .map_err(|_| Error::argument("repetition count must be non-negative"))?;
+                    Ok(Value::Str(str.repeat(int)))
+                }
+                (_, v) => Err(Error::type_error("int", &v)),
+            }
+        }
+        ref v => Err(Error::type_error("number or string", v)),
+    }
+}
+
+// Comparisons are chained: (< a b c) holds if a < b and b < c, (!= a b c) if
+// a != b and b != c.
+
+// Numbers are compared by value, whatever their kind: (= 1 1.0) is true.
+// Anything else is compared like equal? does.
+pub(super) fn eq(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2..);
+
+    chain(args, equal)
+}
+
+pub(super) fn ne(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2..);
+
+    chain(args, |lhs, rhs| Ok(!equal(lhs, rhs)?))
+}
+
+pub(super) fn gt(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2..);
+
+    chain(args, |lhs, rhs| Ok(order(lhs, rhs)?.is_some_and(Ordering::is_gt)))
+}
+
+pub(super) fn lt(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2..);
+
+    chain(args, |lhs, rhs| Ok(order(lhs, rhs)?.is_some_and(Ordering::is_lt)))
+}
+
+pub(super) fn ge(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2..);
+
+    chain(args, |lhs, rhs| Ok(order(lhs, rhs)?.is_some_and(Ordering::is_ge)))
+}
+
+pub(super) fn le(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2..);
+
+    chain(args, |lhs, rhs| Ok(order(lhs, rhs)?.is_some_and(Ordering::is_le)))
+}
+
+// (eqv? a b), also bound to eq?
+// Values without an identity of their own compare by value, which makes the
+// two the same: see `Value::is_eqv`.
+pub(super) fn is_eqv(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2);
+
+    Ok(Value::Bool(args[0].is_eqv(&args[1])))
+}
+
+// (equal? a b)
+// Structural equality, unlike = it doesn't mix kinds of numbers.
+pub(super) fn is_equal(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2);
+
+    Ok(Value::Bool(args[0] == args[1]))
+}
+
+// Whether `holds` is true of every pair of neighbouring arguments.
+fn chain(args: Vec<Value>, holds: impl Fn(Value, Value) -> Result<bool, Error>) -> EResult {
+    for (lhs, rhs) in args.into_iter().tuple_windows() {
+        if !holds(lhs, rhs)? {
+            return Ok(Value::Bool(false));
+        }
+    }
+    Ok(Value::Bool(true))
+}
+
+fn equal(lhs: Value, rhs: Value) -> Result<bool, Error> {
+    if lhs.is_number() && rhs.is_number() {
+        Ok(number::compare(lhs, rhs)? == Some(Ordering::Equal))
+    } else {
+        Ok(lhs == rhs)
+    }
+}
+
+// Numbers are ordered by value, strings lexicographically.
+fn order(lhs: Value, rhs: Value) -> Result<Option<Ordering>, Error> {
+    match (lhs, rhs) {
+        (Value::Str(lhs), Value::Str(rhs)) => Ok(Some(lhs.cmp(&rhs))),
+        (Value::Str(_), v) => Err(Error::type_error("string", &v)),
+        (lhs, rhs) => number::compare(lhs, rhs),
+    }
+}
+
+pub(super) fn def(args: Vec<Value>, env: &mut Env) -> EResult {
+    assert_arg_count!(args, 2);
+
+    use Value::*;
+    match args.into_iter().next_tuple().unwrap() {
+        (Symbol(name), value) => {
+            env.add_binding(name, value);
+            Ok(None)
+        }
+        (v, _) => Err(Error::type_error("symbol", &v)),
+    }
+}
+
+// (load "path")
+// Evaluates the file at `path` (relative to the working directory) in the
+// current environment.
+pub(super) fn load(args: Vec<Value>, env: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    let path = match args.into_iter().next().unwrap() {
+        Value::Str(path) => path,
+        v => return Err(Error::type_error("string", &v)),
+    };
+    let source = std::fs::read_to_string(&path)
+        .map_err(|err| Error::load_failed(&path, err.to_string()))?;
+
+    // Spans in errors refer to the loaded file, so they are rendered here,
+    // while its source is at hand.
+    let ast = Parser::new(Lexer::from(&source[..]))
+        .parse()
+        .map_err(|err| Error::load_failed(&path, err.render(&source)))?;
+    eval_toplevel(ast, env).map_err(|err| Error::load_failed(&path, err.render(&source)))?;
+
+    Ok(Value::None)
+}
+
+// (macroexpand-1 form)
+// Expands `form` once if it is a macro call, returns it unchanged otherwise.
+pub(super) fn macroexpand_1(args: Vec<Value>, env: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    let form = args.into_iter().next().unwrap();
+    Ok(evaluator::macroexpand_1(&form, env)?.unwrap_or(form))
+}
+
+// (macroexpand form)
+// Expands `form` until it no longer is a macro call.
+pub(super) fn macroexpand(args: Vec<Value>, env: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    evaluator::macroexpand(args.into_iter().next().unwrap(), env)
+}
+
+fn argv(argv: &[String], args: Vec<Value>) -> EResult {
+    assert_arg_count!(args, 1);
+
+    match &args[0] {
+        Value::Int(index) => usize::try_from(index)
+            .ok()
+            .and_then(|index| argv.get(index))
+            .map(|arg| Value::Str(arg.clone()))
+            .ok_or_else(|| Error::argument(format!("no argument at index {index}"))),
+        v => Err(Error::type_error("int", v)),
+    }
+}
@@ -0,0 +1,307 @@
+use std::cmp::Ordering;
+
+use itertools::Itertools;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use crate::eval::{
+    env::Env,
+    error::{Arity, EResult, Error},
+    value::Value,
+};
+
+// Numbers are ranked int < ratio < float. When an operation mixes two kinds of
+// numbers, the lower ranked operand is converted to the kind of the other one
+// first: results stay exact until a float gets involved.
+pub(super) enum Coerced {
+    Int(BigInt, BigInt),
+    Ratio(BigRational, BigRational),
+    Float(f64, f64),
+}
+
+pub(super) fn coerce(lhs: Value, rhs: Value) -> Result<Coerced, Error> {
+    use Value::*;
+    Ok(match (lhs, rhs) {
+        (Int(lhs), Int(rhs)) => Coerced::Int(lhs, rhs),
+        (Int(lhs), Ratio(rhs)) => Coerced::Ratio(lhs.into(), rhs),
+        (Ratio(lhs), Int(rhs)) => Coerced::Ratio(lhs, rhs.into()),
+        (Ratio(lhs), Ratio(rhs)) => Coerced::Ratio(lhs, rhs),
+        (Float(lhs), rhs) => Coerced::Float(lhs, to_float(&rhs)?),
+        (lhs, Float(rhs)) => Coerced::Float(to_float(&lhs)?, rhs),
+        (lhs, rhs) if lhs.is_number() => return Err(Error::type_error("number", &rhs)),
+        (lhs, _) => return Err(Error::type_error("number", &lhs)),
+    })
+}
+
+pub(super) fn to_float(value: &Value) -> Result<f64, Error> {
+    match value {
+        Value::Int(value) => Ok(value.to_f64().unwrap_or(f64::NAN)),
+        Value::Ratio(value) => Ok(value.to_f64().unwrap_or(f64::NAN)),
+        Value::Float(value) => Ok(*value),
+        value => Err(Error::type_error("number", value)),
+    }
+}
+
+pub(super) fn add(lhs: Value, rhs: Value) -> EResult {
+    Ok(match coerce(lhs, rhs)? {
+        Coerced::Int(lhs, rhs) => Value::Int(lhs + rhs),
+        Coerced::Ratio(lhs, rhs) => Value::ratio(lhs + rhs),
+        Coerced::Float(lhs, rhs) => Value::Float(lhs + rhs),
+    })
+}
+
+pub(super) fn sub(lhs: Value, rhs: Value) -> EResult {
+    Ok(match coerce(lhs, rhs)? {
+        Coerced::Int(lhs, rhs) => Value::Int(lhs - rhs),
+        Coerced::Ratio(lhs, rhs) => Value::ratio(lhs - rhs),
+        Coerced::Float(lhs, rhs) => Value::Float(lhs - rhs),
+    })
+}
+
+pub(super) fn mul(lhs: Value, rhs: Value) -> EResult {
+    Ok(match coerce(lhs, rhs)? {
+        Coerced::Int(lhs, rhs) => Value::Int(lhs * rhs),
+        Coerced::Ratio(lhs, rhs) => Value::ratio(lhs * rhs),
+        Coerced::Float(lhs, rhs) => Value::Float(lhs * rhs),
+    })
+}
+
+// Dividing two integers gives an exact ratio.
+pub(super) fn div(lhs: Value, rhs: Value) -> EResult {
+    match coerce(lhs, rhs)? {
+        Coerced::Int(_, rhs) if rhs.is_zero() => Err(division_by_zero()),
+        Coerced::Ratio(_, rhs) if rhs.is_zero() => Err(division_by_zero()),
+        Coerced::Int(lhs, rhs) => Ok(Value::ratio(BigRational::new(lhs, rhs))),
+        Coerced::Ratio(lhs, rhs) => Ok(Value::ratio(lhs / rhs)),
+        Coerced::Float(lhs, rhs) => Ok(Value::Float(lhs / rhs)),
+    }
+}
+
+// `None` if either side is NaN.
+pub(super) fn compare(lhs: Value, rhs: Value) -> Result<Option<Ordering>, Error> {
+    Ok(match coerce(lhs, rhs)? {
+        Coerced::Int(lhs, rhs) => Some(lhs.cmp(&rhs)),
+        Coerced::Ratio(lhs, rhs) => Some(lhs.cmp(&rhs)),
+        Coerced::Float(lhs, rhs) => lhs.partial_cmp(&rhs),
+    })
+}
+
+// -------------------------------------------------------------------------- //
+// Builtins                                                                   //
+// -------------------------------------------------------------------------- //
+
+// (- x) negates x, (- x y ...) subtracts the ys from x
+pub(super) fn subtract(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1..);
+
+    let mut args = args.into_iter();
+    let first = args.next().unwrap();
+    if args.len() == 0 {
+        return sub(Value::Int(0.into()), first);
+    }
+    args.try_fold(first, sub)
+}
+
+// (/ x) is 1/x, (/ x y ...) divides x by the ys
+pub(super) fn divide(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1..);
+
+    let mut args = args.into_iter();
+    let first = args.next().unwrap();
+    if args.len() == 0 {
+        return div(Value::Int(1.into()), first);
+    }
+    args.try_fold(first, div)
+}
+
+// (quotient n d), rounded towards zero
+pub(super) fn quotient(args: Vec<Value>, _: &mut Env) -> EResult {
+    let (n, d) = division_args(args)?;
+    Ok(Value::Int(n / d))
+}
+
+// (remainder n d), has the sign of n
+pub(super) fn remainder(args: Vec<Value>, _: &mut Env) -> EResult {
+    let (n, d) = division_args(args)?;
+    Ok(Value::Int(n % d))
+}
+
+// (modulo n d), has the sign of d
+pub(super) fn modulo(args: Vec<Value>, _: &mut Env) -> EResult {
+    let (n, d) = division_args(args)?;
+    Ok(Value::Int(n.mod_floor(&d)))
+}
+
+// (abs x)
+pub(super) fn abs(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    match args.into_iter().next().unwrap() {
+        Value::Int(value) => Ok(Value::Int(value.abs())),
+        Value::Ratio(value) => Ok(Value::Ratio(value.abs())),
+        Value::Float(value) => Ok(Value::Float(value.abs())),
+        v => Err(Error::type_error("number", &v)),
+    }
+}
+
+// (min x ...)
+pub(super) fn min(args: Vec<Value>, _: &mut Env) -> EResult {
+    extremum(args, Ordering::Less)
+}
+
+// (max x ...)
+pub(super) fn max(args: Vec<Value>, _: &mut Env) -> EResult {
+    extremum(args, Ordering::Greater)
+}
+
+// The largest exact power `expt` computes, in bits. Anything bigger would take
+// ages to compute and more memory than is reasonable to print.
+const MAX_EXPT_BITS: u64 = 1 << 24;
+
+// (expt base power)
+// Exact if base is exact and power is an integer.
+pub(super) fn expt(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2);
+
+    match args.into_iter().next_tuple().unwrap() {
+        (base @ (Value::Int(_) | Value::Ratio(_)), Value::Int(power)) => {
+            let power = power
+                .to_i32()
+                .ok_or_else(|| Error::argument("exponent is too large"))?;
+            let base = match base {
+                Value::Int(base) => BigRational::from(base),
+                Value::Ratio(base) => base,
+                _ => unreachable!(),
+            };
+            if power < 0 && base.is_zero() {
+                return Err(division_by_zero());
+            }
+            // Roughly the size of the result: 0, 1 and -1 stay small whatever
+            // the power
+            let bits = base.numer().bits().max(base.denom().bits()).saturating_sub(1);
+            if bits.saturating_mul(power.unsigned_abs().into()) > MAX_EXPT_BITS {
+                return Err(Error::argument("exponent is too large"));
+            }
+            Ok(Value::ratio(base.pow(power)))
+        }
+        (base, power) => Ok(Value::Float(to_float(&base)?.powf(to_float(&power)?))),
+    }
+}
+
+// (gcd n ...), 0 without arguments
+pub(super) fn gcd(args: Vec<Value>, _: &mut Env) -> EResult {
+    args.into_iter()
+        .try_fold(BigInt::zero(), |acc, value| Ok(acc.gcd(&to_int(value)?)))
+        .map(Value::Int)
+}
+
+// (lcm n ...), 1 without arguments
+pub(super) fn lcm(args: Vec<Value>, _: &mut Env) -> EResult {
+    args.into_iter()
+        .try_fold(BigInt::from(1), |acc, value| Ok(acc.lcm(&to_int(value)?)))
+        .map(Value::Int)
+}
+
+// (sqrt x)
+// Exact for exact perfect squares, a float otherwise.
+pub(super) fn sqrt(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    let exact_sqrt = |n: &BigInt| {
+        let root = n.sqrt();
+        (&root * &root == *n).then_some(root)
+    };
+    match args.into_iter().next().unwrap() {
+        Value::Int(n) if !n.is_negative() => match exact_sqrt(&n) {
+            Some(root) => Ok(Value::Int(root)),
+            None => Ok(Value::Float(to_float(&Value::Int(n))?.sqrt())),
+        },
+        Value::Ratio(r) if r.is_positive() => match (exact_sqrt(r.numer()), exact_sqrt(r.denom())) {
+            (Some(numer), Some(denom)) => Ok(Value::ratio(BigRational::new(numer, denom))),
+            _ => Ok(Value::Float(to_float(&Value::Ratio(r))?.sqrt())),
+        },
+        v => Ok(Value::Float(to_float(&v)?.sqrt())),
+    }
+}
+
+// (exact->inexact x)
+pub(super) fn exact_to_inexact(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    to_float(&args[0]).map(Value::Float)
+}
+
+// (zero? x)
+pub(super) fn is_zero(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    let value = args.into_iter().next().unwrap();
+    Ok(Value::Bool(compare(value, Value::Int(0.into()))? == Some(Ordering::Equal)))
+}
+
+// (even? n)
+pub(super) fn is_even(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Bool(to_int(args.into_iter().next().unwrap())?.is_even()))
+}
+
+// (odd? n)
+pub(super) fn is_odd(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Bool(to_int(args.into_iter().next().unwrap())?.is_odd()))
+}
+
+// -------------------------------------------------------------------------- //
+// Helpers                                                                    //
+// -------------------------------------------------------------------------- //
+
+fn to_int(value: Value) -> Result<BigInt, Error> {
+    match value {
+        Value::Int(value) => Ok(value),
+        v => Err(Error::type_error("int", &v)),
+    }
+}
+
+// The two integer arguments of quotient, remainder and modulo.
+fn division_args(args: Vec<Value>) -> Result<(BigInt, BigInt), Error> {
+    assert_arg_count!(args, 2);
+
+    let (n, d) = args.into_iter().next_tuple().unwrap();
+    let (n, d) = (to_int(n)?, to_int(d)?);
+    if d.is_zero() {
+        return Err(division_by_zero());
+    }
+    Ok((n, d))
+}
+
+// The argument that compares as `wanted` to all the others. The result is a
+// float if any of the arguments is one.
+fn extremum(args: Vec<Value>, wanted: Ordering) -> EResult {
+    assert_arg_count!(args, 1..);
+
+    let inexact = args.iter().any(|value| matches!(value, Value::Float(_)));
+    let mut args = args.into_iter();
+    let mut best = args.next().unwrap();
+    if !best.is_number() {
+        return Err(Error::type_error("number", &best));
+    }
+    for value in args {
+        if compare(value.clone(), best.clone())? == Some(wanted) {
+            best = value;
+        }
+    }
+
+    if inexact {
+        to_float(&best).map(Value::Float)
+    } else {
+        Ok(best)
+    }
+}
+
+fn division_by_zero() -> Error {
+    Error::argument("division by zero")
+}
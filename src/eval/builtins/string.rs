@@ -0,0 +1,243 @@
+use itertools::Itertools;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::eval::{
+    env::Env,
+    error::{Arity, EResult, Error},
+    value::Value,
+};
+use crate::{lexer::Lexer, token::TokenKind};
+
+use super::{char::to_char, list::list_items};
+
+// Strings are indexed by characters (Unicode scalar values), neither by bytes
+// nor by grapheme clusters: (string-length "é") is 1 if it is written as a
+// single code point, 2 if it is an e followed by a combining accent.
+//
+// Functions that search for something in a string accept either a string or a
+// character to search for.
+
+// (string-ref string index)
+pub(super) fn string_ref(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2);
+
+    match args.into_iter().next_tuple().unwrap() {
+        (Value::Str(string), Value::Int(index)) => index
+            .to_usize()
+            .and_then(|index| string.chars().nth(index))
+            .map(Value::Char)
+            .ok_or_else(|| Error::argument(format!("index {index} is out of range"))),
+        (Value::Str(_), v) => Err(Error::type_error("int", &v)),
+        (v, _) => Err(Error::type_error("string", &v)),
+    }
+}
+
+// (string->list string)
+pub(super) fn string_to_list(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    match &args[0] {
+        Value::Str(string) => Ok(Value::list(string.chars().map(Value::Char).collect_vec())),
+        v => Err(Error::type_error("string", v)),
+    }
+}
+
+// (list->string list)
+pub(super) fn list_to_string(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    list_items(&args[0])?
+        .iter()
+        .map(to_char)
+        .collect::<Result<String, _>>()
+        .map(Value::Str)
+}
+
+// (string-length string)
+pub(super) fn string_length(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Int(to_str(&args[0])?.chars().count().into()))
+}
+
+// (substring string start [end])
+// The characters from start up to, but not including, end.
+pub(super) fn substring(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2..=3);
+
+    let string = to_str(&args[0])?;
+    let len = string.chars().count();
+    let start = to_index(&args[1])?;
+    let end = args.get(2).map(to_index).transpose()?.unwrap_or(len);
+    if start > end || end > len {
+        return Err(Error::argument(format!(
+            "range {start}..{end} is out of bounds for a string of length {len}"
+        )));
+    }
+    Ok(Value::Str(string.chars().skip(start).take(end - start).collect()))
+}
+
+// (string-index string pattern)
+// The index of the first occurrence of pattern, false if there is none.
+pub(super) fn string_index(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2);
+
+    let (string, pattern) = (to_str(&args[0])?, to_pattern(&args[1])?);
+    Ok(match string.find(&pattern) {
+        Some(byte) => Value::Int(string[..byte].chars().count().into()),
+        None => Value::Bool(false),
+    })
+}
+
+// (string-split string [separator])
+// Without a separator, splits on runs of whitespace.
+pub(super) fn string_split(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1..=2);
+
+    let string = to_str(&args[0])?;
+    let parts: Vec<_> = match args.get(1) {
+        Some(separator) => {
+            let separator = to_non_empty_pattern(separator)?;
+            string.split(&separator).map(|part| Value::Str(part.into())).collect()
+        }
+        None => string.split_whitespace().map(|part| Value::Str(part.into())).collect(),
+    };
+    Ok(Value::list(parts))
+}
+
+// (string-join list [separator])
+pub(super) fn string_join(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1..=2);
+
+    let separator = args.get(1).map(to_pattern).transpose()?.unwrap_or_default();
+    let parts = list_items(&args[0])?;
+    let parts = parts.iter().map(to_str).collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::Str(parts.join(&separator)))
+}
+
+// (string-trim string)
+// Removes leading and trailing whitespace.
+pub(super) fn string_trim(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Str(to_str(&args[0])?.trim().into()))
+}
+
+// (string-upcase string)
+// The case mappings are the full Unicode ones, the length may change: ß
+// becomes SS.
+pub(super) fn string_upcase(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Str(to_str(&args[0])?.to_uppercase()))
+}
+
+// (string-downcase string)
+pub(super) fn string_downcase(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Str(to_str(&args[0])?.to_lowercase()))
+}
+
+// (string-contains? string pattern)
+pub(super) fn is_string_contains(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2);
+
+    Ok(Value::Bool(to_str(&args[0])?.contains(&to_pattern(&args[1])?)))
+}
+
+// (string-replace string pattern replacement)
+// Replaces every occurrence of pattern.
+pub(super) fn string_replace(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 3);
+
+    let string = to_str(&args[0])?;
+    let (pattern, replacement) = (to_non_empty_pattern(&args[1])?, to_pattern(&args[2])?);
+    Ok(Value::Str(string.replace(&pattern, &replacement)))
+}
+
+// (string->number string)
+// Accepts the same syntax as number literals, returns false if string isn't
+// exactly one: surrounding whitespace or comments don't count as part of it.
+pub(super) fn string_to_number(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    let string = to_str(&args[0])?;
+    let mut tokens = Lexer::from(string);
+    let number = match (tokens.next(), tokens.next()) {
+        (Some(Ok(token)), None) if token.span.start == 0 && token.span.end == string.len() => {
+            match token.kind {
+                TokenKind::Integer(int) => int.parse::<BigInt>().ok().map(Value::Int),
+                TokenKind::Float(float) => float.parse().ok().map(Value::Float),
+                TokenKind::Ratio(ratio) => ratio.parse().ok().map(Value::ratio),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    Ok(number.unwrap_or(Value::Bool(false)))
+}
+
+// (number->string number)
+pub(super) fn number_to_string(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    match &args[0] {
+        number if number.is_number() => Ok(Value::Str(number.to_string())),
+        v => Err(Error::type_error("number", v)),
+    }
+}
+
+// (string->symbol string)
+pub(super) fn string_to_symbol(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Symbol(to_str(&args[0])?.into()))
+}
+
+// (symbol->string symbol)
+pub(super) fn symbol_to_string(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    match &args[0] {
+        Value::Symbol(name) => Ok(Value::Str(name.clone())),
+        v => Err(Error::type_error("symbol", v)),
+    }
+}
+
+// -------------------------------------------------------------------------- //
+// Helpers                                                                    //
+// -------------------------------------------------------------------------- //
+
+fn to_str(value: &Value) -> Result<&str, Error> {
+    match value {
+        Value::Str(string) => Ok(string),
+        v => Err(Error::type_error("string", v)),
+    }
+}
+
+fn to_pattern(value: &Value) -> Result<String, Error> {
+    match value {
+        Value::Str(string) => Ok(string.clone()),
+        Value::Char(c) => Ok(c.to_string()),
+        v => Err(Error::type_error("string or char", v)),
+    }
+}
+
+// Splitting on or replacing the empty string has no sensible meaning.
+fn to_non_empty_pattern(value: &Value) -> Result<String, Error> {
+    match to_pattern(value)? {
+        pattern if pattern.is_empty() => Err(Error::argument("pattern must not be empty")),
+        pattern => Ok(pattern),
+    }
+}
+
+fn to_index(value: &Value) -> Result<usize, Error> {
+    match value {
+        Value::Int(index) => index
+            .to_usize()
+            .ok_or_else(|| Error::argument(format!("{index} is not a valid index"))),
+        v => Err(Error::type_error("int", v)),
+    }
+}
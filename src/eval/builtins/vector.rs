@@ -0,0 +1,92 @@
+use std::{cell::RefCell, rc::Rc};
+
+use itertools::Itertools;
+use num_traits::ToPrimitive;
+
+use crate::eval::{
+    env::Env,
+    error::{Arity, EResult, Error},
+    value::Value,
+};
+
+// (vector-ref vector index)
+pub(super) fn vector_ref(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 2);
+
+    let items = to_vector(&args[0])?.borrow();
+    let index = to_index(&args[1], items.len())?;
+    Ok(items[index].clone())
+}
+
+// (vector-set! vector index value)
+pub(super) fn vector_set(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 3);
+
+    let (vector, index, value) = args.into_iter().next_tuple().unwrap();
+    let mut items = to_vector(&vector)?.borrow_mut();
+    let index = to_index(&index, items.len())?;
+    items[index] = value;
+    Ok(Value::None)
+}
+
+// (vector-length vector)
+pub(super) fn vector_length(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::Int(to_vector(&args[0])?.borrow().len().into()))
+}
+
+// (vector->list vector)
+pub(super) fn vector_to_list(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1);
+
+    Ok(Value::list(to_vector(&args[0])?.borrow().clone()))
+}
+
+// (make-vector length [fill]), fill defaults to false
+pub(super) fn make_vector(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 1..=2);
+
+    let mut args = args.into_iter();
+    let length = match args.next().unwrap() {
+        Value::Int(length) => length
+            .to_usize()
+            .ok_or_else(|| Error::argument(format!("{length} is not a valid length")))?,
+        v => return Err(Error::type_error("int", &v)),
+    };
+    let fill = args.next().unwrap_or(Value::Bool(false));
+    Ok(Value::vector(vec![fill; length]))
+}
+
+// (subvector vector start end)
+// A new vector with the items from start up to, but not including, end.
+pub(super) fn subvector(args: Vec<Value>, _: &mut Env) -> EResult {
+    assert_arg_count!(args, 3);
+
+    let items = to_vector(&args[0])?.borrow();
+    let end = to_index(&args[2], items.len() + 1)?;
+    let start = to_index(&args[1], end + 1)?;
+    Ok(Value::vector(items[start..end].to_vec()))
+}
+
+// -------------------------------------------------------------------------- //
+// Helpers                                                                    //
+// -------------------------------------------------------------------------- //
+
+fn to_vector(value: &Value) -> Result<&Rc<RefCell<Vec<Value>>>, Error> {
+    match value {
+        Value::Vector(items) => Ok(items),
+        v => Err(Error::type_error("vector", v)),
+    }
+}
+
+// An index below `len`.
+fn to_index(value: &Value, len: usize) -> Result<usize, Error> {
+    match value {
+        Value::Int(index) => index
+            .to_usize()
+            .filter(|&index| index < len)
+            .ok_or_else(|| Error::argument(format!("index {index} is out of range"))),
+        v => Err(Error::type_error("int", v)),
+    }
+}
@@ -1,29 +1,233 @@
-use std::collections::HashMap;
+use std::{
+    cell::{Ref, RefCell},
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
 
-use super::value::Value;
+use super::{
+    expander::SyntaxRules,
+    value::{Lambda, Value},
+};
 
+// An environment is a chain of frames, each pointing to the frame it was
+// created in. Frames are reference counted, so cloning an `Env` is cheap and
+// shares the bindings: this is what lets a lambda see the bindings of the
+// place it was defined in rather than the place it is called from.
+//
+// A lambda stored in the frame it closes over (with `letrec`, or a `def`
+// inside a function body) forms a reference cycle that reference counting
+// alone never frees. Such cycles are found and broken by `collect_cycles`.
+#[derive(Clone)]
 pub struct Env {
-    scopes: Vec<HashMap<String, Value>>,
+    frame: Rc<Frame>,
+    // Syntax definitions are expanded away before evaluation, so they aren't
+    // scoped like bindings: there is one table for the whole environment.
+    syntax: Rc<RefCell<HashMap<String, Rc<SyntaxRules>>>>,
+}
+
+// Macros live in their own namespace, next to the bindings.
+struct Frame {
+    bindings: RefCell<HashMap<String, Value>>,
+    macros: RefCell<HashMap<String, Rc<Lambda>>>,
+    parent: Option<Rc<Frame>>,
 }
 
 impl Env {
     pub fn new(builtins: HashMap<String, Value>) -> Self {
         Env {
-            scopes: vec![builtins],
+            frame: Frame::register(Frame {
+                bindings: RefCell::new(builtins),
+                macros: RefCell::new(HashMap::new()),
+                parent: None,
+            }),
+            syntax: Rc::default(),
         }
     }
 
-    pub fn enter_scope(&mut self) {
-        self.scopes.push(HashMap::new());
-    }
-    pub fn exit_scope(&mut self) {
-        self.scopes.pop();
+    // Creates a new, empty frame whose parent is the current one.
+    pub fn extend(&self) -> Self {
+        Env {
+            frame: Frame::register(Frame {
+                bindings: RefCell::new(HashMap::new()),
+                macros: RefCell::new(HashMap::new()),
+                parent: Some(self.frame.clone()),
+            }),
+            syntax: self.syntax.clone(),
+        }
     }
 
+    // Binds `name` in the current frame, shadowing any outer binding.
     pub fn add_binding(&mut self, name: String, value: Value) {
-        self.scopes.last_mut().unwrap().insert(name, value);
+        self.frame.bindings.borrow_mut().insert(name, value);
+    }
+    pub fn get_binding(&self, name: &str) -> Option<Value> {
+        self.frames()
+            .find_map(|frame| frame.bindings.borrow().get(name).cloned())
+    }
+    // The binding of `name` in the outermost frame, ignoring any that shadow it.
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        let global = self.frames().last().unwrap();
+        global.bindings.borrow().get(name).cloned()
+    }
+    // Rebinds the nearest existing binding of `name`. Returns `false` if
+    // there is none.
+    pub fn set_binding(&mut self, name: String, value: Value) -> bool {
+        match self.frames().find(|frame| frame.bindings.borrow().contains_key(&name)) {
+            Some(frame) => {
+                frame.bindings.borrow_mut().insert(name, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn add_macro(&mut self, name: String, expander: Rc<Lambda>) {
+        self.frame.macros.borrow_mut().insert(name, expander);
+    }
+    pub fn get_macro(&self, name: &str) -> Option<Rc<Lambda>> {
+        self.frames()
+            .find_map(|frame| frame.macros.borrow().get(name).cloned())
+    }
+
+    pub(super) fn define_syntax(&self, name: String, rules: SyntaxRules) {
+        self.syntax.borrow_mut().insert(name, Rc::new(rules));
+    }
+    pub(super) fn get_syntax(&self, name: &str) -> Option<Rc<SyntaxRules>> {
+        self.syntax.borrow().get(name).cloned()
+    }
+
+    fn frames(&self) -> impl Iterator<Item = &Frame> {
+        std::iter::successors(Some(&*self.frame), |frame| frame.parent.as_deref())
+    }
+}
+
+// -------------------------------------------------------------------------- //
+// Cycle collection                                                           //
+// -------------------------------------------------------------------------- //
+
+// Below this many frames, looking for cycles isn't worth it.
+const MIN_COLLECTION_THRESHOLD: usize = 1024;
+
+struct Registry {
+    // Every frame created on this thread that was alive at the last collection
+    // or was created since.
+    frames: Vec<Weak<Frame>>,
+    // The next collection happens once `frames` grows this long. It is twice
+    // the number of frames left by the last one, so that collections take
+    // amortized constant time per frame.
+    threshold: usize,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = const {
+        RefCell::new(Registry { frames: Vec::new(), threshold: MIN_COLLECTION_THRESHOLD })
+    };
+}
+
+impl Frame {
+    fn register(frame: Frame) -> Rc<Frame> {
+        let frame = Rc::new(frame);
+        let collect = REGISTRY.with(|registry| {
+            let mut registry = registry.borrow_mut();
+            registry.frames.push(Rc::downgrade(&frame));
+            registry.frames.len() >= registry.threshold
+        });
+        if collect {
+            collect_cycles();
+        }
+        frame
+    }
+}
+
+// Empties the frames that are only referenced by lambdas bound in frames that
+// are garbage too, which drops the lambdas and then the frames themselves.
+//
+// The graph searched has the frames and the lambdas bound directly in them as
+// nodes, linked by the parents of frames, the bindings of frames and the
+// environments of lambdas. A node is live if it has more references than the
+// graph accounts for: those come from the evaluator, or from values the search
+// doesn't look into, like lists. Anything live nodes lead to is live as well,
+// and whatever remains can't be reached from outside the graph.
+fn collect_cycles() {
+    let frames: Vec<Rc<Frame>> = REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.frames.retain(|frame| frame.strong_count() > 0);
+        registry.frames.iter().filter_map(Weak::upgrade).collect()
+    });
+    let frame_ids: HashMap<*const Frame, usize> =
+        frames.iter().enumerate().map(|(id, frame)| (Rc::as_ptr(frame), id)).collect();
+
+    // A frame that is being modified is left alone, along with everything it
+    // leads to.
+    let contents: Vec<Option<(Ref<_>, Ref<_>)>> = frames
+        .iter()
+        .map(|frame| Some((frame.bindings.try_borrow().ok()?, frame.macros.try_borrow().ok()?)))
+        .collect();
+
+    // Lambdas are numbered after the frames.
+    let mut lambdas: Vec<&Rc<Lambda>> = Vec::new();
+    let mut lambda_ids: HashMap<*const Lambda, usize> = HashMap::new();
+    let mut edges: Vec<Vec<usize>> = vec![Vec::new(); frames.len()];
+    for (id, frame) in frames.iter().enumerate() {
+        if let Some(parent) = &frame.parent {
+            edges[id].extend(frame_ids.get(&Rc::as_ptr(parent)));
+        }
+        let Some((bindings, macros)) = &contents[id] else {
+            continue;
+        };
+        let bound = bindings
+            .values()
+            .filter_map(|value| match value {
+                Value::Lambda(lambda) => Some(lambda),
+                _ => None,
+            })
+            .chain(macros.values());
+        for lambda in bound {
+            let lambda_id = *lambda_ids.entry(Rc::as_ptr(lambda)).or_insert_with(|| {
+                lambdas.push(lambda);
+                edges.push(Vec::new());
+                edges.len() - 1
+            });
+            edges[id].push(lambda_id);
+        }
+    }
+    for (id, lambda) in lambdas.iter().enumerate() {
+        let env = frame_ids.get(&Rc::as_ptr(&lambda.env.frame));
+        edges[frames.len() + id].extend(env);
     }
-    pub fn get_binding(&self, name: String) -> Option<&Value> {
-        self.scopes.iter().rev().find_map(|scope| scope.get(&name))
+
+    let mut references = vec![0; edges.len()];
+    for &target in edges.iter().flatten() {
+        references[target] += 1;
+    }
+    let mut live: Vec<bool> = (0..edges.len())
+        .map(|id| match frames.get(id) {
+            // Not counting the reference held by `frames`
+            Some(frame) => contents[id].is_none() || Rc::strong_count(frame) - 1 > references[id],
+            None => Rc::strong_count(lambdas[id - frames.len()]) > references[id],
+        })
+        .collect();
+    let mut pending: Vec<usize> = (0..edges.len()).filter(|&id| live[id]).collect();
+    while let Some(id) = pending.pop() {
+        for &target in &edges[id] {
+            if !live[target] {
+                live[target] = true;
+                pending.push(target);
+            }
+        }
     }
+    drop(contents);
+
+    let garbage: Vec<_> = frames
+        .iter()
+        .zip(&live)
+        .filter(|(_, &live)| !live)
+        .map(|(frame, _)| (frame.bindings.take(), frame.macros.take()))
+        .collect();
+    drop(garbage);
+
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.threshold = (2 * registry.frames.len()).max(MIN_COLLECTION_THRESHOLD);
+    });
 }
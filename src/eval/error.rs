@@ -0,0 +1,140 @@
+use std::fmt;
+
+use crate::span::{Span, Spanned};
+
+use super::value::Value;
+
+pub type EResult = Result<Value, Error>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    // Name of the function or special form the error was raised in, if known.
+    pub function: Option<String>,
+    // Innermost expression that was being evaluated when the error occurred.
+    pub span: Option<Span>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    ArityMismatch { expected: Arity, got: usize },
+    TypeError { expected: &'static str, found: &'static str },
+    Unbound(String),
+    NotCallable(&'static str),
+    InvalidSyntax(String),
+    InvalidArgument(String),
+    LoadFailed { path: String, reason: String },
+}
+
+// Number of arguments a function accepts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+    Between(usize, usize),
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Self {
+        Self { kind, function: None, span: None }
+    }
+
+    pub fn arity(expected: Arity, got: usize) -> Self {
+        Self::new(ErrorKind::ArityMismatch { expected, got })
+    }
+    pub fn type_error(expected: &'static str, found: &Value) -> Self {
+        Self::new(ErrorKind::TypeError { expected, found: found.type_name() })
+    }
+    pub fn unbound(name: String) -> Self {
+        Self::new(ErrorKind::Unbound(name))
+    }
+    pub fn not_callable(value: &Value) -> Self {
+        Self::new(ErrorKind::NotCallable(value.type_name()))
+    }
+    pub fn syntax(reason: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidSyntax(reason.into()))
+    }
+    pub fn argument(reason: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidArgument(reason.into()))
+    }
+    pub fn load_failed(path: &str, reason: impl Into<String>) -> Self {
+        Self::new(ErrorKind::LoadFailed { path: path.to_string(), reason: reason.into() })
+    }
+
+    // Attributes the error to `name`, unless it has already been attributed to
+    // a more specific function.
+    pub fn in_function(mut self, name: &str) -> Self {
+        self.function.get_or_insert_with(|| name.to_string());
+        self
+    }
+    // Locates the error at `span`, unless it has already been located more
+    // precisely.
+    pub fn at(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Self::Exactly(n) => count == n,
+            Self::AtLeast(n) => count >= n,
+            Self::Between(min, max) => (min..=max).contains(&count),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------- //
+// Trait implementations                                                      //
+// -------------------------------------------------------------------------- //
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.function {
+            Some(name) => write!(f, "error in `{name}`")?,
+            None => write!(f, "error")?,
+        }
+        if let Some(span) = self.span {
+            write!(f, " at {span}")?;
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ArityMismatch { expected, got } => {
+                write!(f, "expected {expected}, got {got}")
+            }
+            Self::TypeError { expected, found } => {
+                write!(f, "type mismatch: expected {expected}, found {found}")
+            }
+            Self::Unbound(name) => write!(f, "unbound identifier `{name}`"),
+            Self::NotCallable(found) => write!(f, "{found} is not callable"),
+            Self::InvalidSyntax(reason) => write!(f, "invalid syntax: {reason}"),
+            Self::InvalidArgument(reason) => write!(f, "invalid argument: {reason}"),
+            Self::LoadFailed { path, reason } => write!(f, "failed to load {path:?}:\n{reason}"),
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        match *self {
+            Self::Exactly(n) => write!(f, "{n} argument{}", plural(n)),
+            Self::AtLeast(n) => write!(f, "at least {n} argument{}", plural(n)),
+            Self::Between(min, max) => write!(f, "{min} to {max} arguments"),
+        }
+    }
+}
+
+impl Spanned for Error {
+    fn span(&self) -> Option<Span> {
+        self.span
+    }
+}
+
+impl std::error::Error for Error {}
@@ -1,55 +1,412 @@
-use crate::ast::{Expr, TopLevel};
+use std::rc::Rc;
 
-use super::{env::Env, value::Value};
+use itertools::Itertools;
 
-type EResult = Result<Value, ()>;
+use crate::ast::{Expr, ExprKind, TopLevel};
+use crate::span::Span;
+
+use super::{
+    env::Env,
+    error::{Arity, EResult, Error},
+    expander::expand,
+    keyword::Keyword,
+    special_forms::{self, eval_body},
+    value::{Lambda, Value},
+};
 
 pub fn evaluate_toplevel(ast: TopLevel) -> EResult {
     eval_toplevel(ast, &mut Env::default())
 }
 
-fn eval_toplevel(ast: TopLevel, env: &mut Env) -> EResult {
+// Evaluates `ast` in an existing environment, so that definitions persist
+// across calls.
+pub fn eval_toplevel(ast: TopLevel, env: &mut Env) -> EResult {
+    // Stops at the first error, a script must not carry on after a failure.
+    // Each expression is expanded right before it is evaluated, so that it
+    // can use the syntax defined by the ones before it.
     ast.0
         .into_iter()
-        .map(|expr| eval_expr(expr, env))
-        .last()
-        .unwrap_or(Ok(Value::None))
-}
-
-fn eval_expr(ast: Expr, env: &mut Env) -> EResult {
-    match ast {
-        Expr::List(body) => eval_list(body, env),
-        Expr::Ident(name) => lookup_indent(name, env),
-        Expr::Int(value) => Ok(Value::Int(value)),
-        Expr::Str(value) => Ok(Value::Str(value)),
-        Expr::Bool(value) => Ok(Value::Bool(value)),
-        Expr::Quoted(expr) => match *expr {
-            Expr::Quoted(_) | Expr::Int(_) | Expr::Str(_) | Expr::Bool(_) => unreachable!(),
-            Expr::List(_) => unimplemented!(),
-            Expr::Ident(name) => Ok(Value::Symbol(name)),
-        },
-    }
-}
-
-fn eval_list(body: Vec<Expr>, env: &mut Env) -> EResult {
-    match body.len() {
-        0 => Ok(Value::Nil),
-        n => {
-            let mut values = body.into_iter().map(|item| eval_expr(item, env));
-            let first = values.next().unwrap()?;
-            let rest = values.collect::<Result<Vec<_>, _>>()?;
-
-            if let Value::Fun(fun) = first {
-                fun(rest, env)
-            } else if n == 1 {
-                Ok(first)
-            } else {
-                Err(())
+        .try_fold(Value::None, |_, expr| eval_expr(&expand(expr, env)?, env))
+}
+
+// What is left to do after a step of evaluation. Expressions in tail position
+// (the branches of an `if`, the last expression of a body, ...) are returned
+// rather than evaluated, and `run` loops over them: tail calls therefore run in
+// constant stack space.
+//
+// Code is evaluated by reference, a step borrows the expression it came from.
+// Calls to lambdas and macro expansions return the code to run next instead,
+// so that `run` can keep it alive: a tail call costs the same no matter how
+// large the body of the lambda is.
+pub(super) enum Step<'a> {
+    Done(Value),
+    Eval(&'a Expr, Env),
+    // The body of a lambda, in the scope its arguments are bound in
+    Call(Rc<Lambda>, Env),
+    // The code a macro call expanded into
+    Expanded(Expr, Env),
+}
+
+pub(super) type StepResult<'a> = Result<Step<'a>, Error>;
+
+// What is left to do once the steps that borrow from the current code ran out.
+enum Settled {
+    Done(Value),
+    Call(Rc<Lambda>, Env),
+    Expanded(Expr, Env),
+}
+
+pub(super) fn eval_expr(ast: &Expr, env: &mut Env) -> EResult {
+    run(eval_step(ast, env)?)
+}
+
+// Each iteration owns the code that the steps of `settle` borrow from.
+fn run(step: Step) -> EResult {
+    let mut next = settle(step)?;
+    loop {
+        next = match next {
+            Settled::Done(value) => return Ok(value),
+            Settled::Call(lambda, mut scope) => settle(eval_body(&lambda.body, &mut scope)?)?,
+            Settled::Expanded(expr, mut env) => settle(eval_step(&expr, &mut env)?)?,
+        };
+    }
+}
+
+fn settle(mut step: Step) -> Result<Settled, Error> {
+    loop {
+        step = match step {
+            Step::Done(value) => return Ok(Settled::Done(value)),
+            Step::Eval(expr, mut env) => eval_step(expr, &mut env)?,
+            Step::Call(lambda, scope) => return Ok(Settled::Call(lambda, scope)),
+            Step::Expanded(expr, env) => return Ok(Settled::Expanded(expr, env)),
+        }
+    }
+}
+
+fn eval_step<'a>(ast: &'a Expr, env: &mut Env) -> StepResult<'a> {
+    let span = ast.span;
+    let value = match &ast.kind {
+        ExprKind::List(body) => return eval_list(body, span, env).map_err(|err| err.at(span)),
+        ExprKind::Vector(items) => eval_items(items, env).map(Value::vector),
+        ExprKind::Map(items) => eval_items(items, env).and_then(map_from),
+        ExprKind::Set(items) => eval_items(items, env).map(set_from),
+        ExprKind::Ident(name) => lookup_indent(name, env),
+        ExprKind::Keyword(name) => Ok(Value::Keyword(Keyword::intern(name))),
+        ExprKind::Int(value) => Ok(Value::Int(value.clone())),
+        ExprKind::Float(value) => Ok(Value::Float(*value)),
+        ExprKind::Ratio(value) => Ok(Value::ratio(value.clone())),
+        ExprKind::Str(value) => Ok(Value::Str(value.clone())),
+        ExprKind::Char(value) => Ok(Value::Char(*value)),
+        ExprKind::Bool(value) => Ok(Value::Bool(*value)),
+        ExprKind::Quoted(expr) => eval_quoted(expr),
+        ExprKind::Quasiquoted(expr) => eval_quasiquoted(expr, 1, env),
+        ExprKind::Unquoted(_) | ExprKind::UnquoteSpliced(_) => {
+            Err(Error::syntax("unquote outside of a quasiquote"))
+        }
+    };
+    value.map(Step::Done).map_err(|err| err.at(span))
+}
+
+pub(super) fn eval_quoted(datum: &Expr) -> EResult {
+    match &datum.kind {
+        ExprKind::Int(value) => Ok(Value::Int(value.clone())),
+        ExprKind::Float(value) => Ok(Value::Float(*value)),
+        ExprKind::Ratio(value) => Ok(Value::ratio(value.clone())),
+        ExprKind::Str(value) => Ok(Value::Str(value.clone())),
+        ExprKind::Char(value) => Ok(Value::Char(*value)),
+        ExprKind::Bool(value) => Ok(Value::Bool(*value)),
+        // ''x is (quote x), and so on
+        ExprKind::Quoted(datum) => Ok(wrap("quote", eval_quoted(datum)?)),
+        ExprKind::Quasiquoted(datum) => Ok(wrap("quasiquote", eval_quoted(datum)?)),
+        ExprKind::Unquoted(datum) => Ok(wrap("unquote", eval_quoted(datum)?)),
+        ExprKind::UnquoteSpliced(datum) => Ok(wrap("unquote-splicing", eval_quoted(datum)?)),
+        ExprKind::List(items) => eval_quoted_list(items),
+        ExprKind::Vector(items) => eval_quoted_items(items).map(Value::vector),
+        ExprKind::Map(items) => eval_quoted_items(items).and_then(map_from),
+        ExprKind::Set(items) => eval_quoted_items(items).map(set_from),
+        ExprKind::Ident(name) if name == "." => {
+            Err(Error::syntax("`.` can only appear in a list").at(datum.span))
+        }
+        ExprKind::Ident(name) => Ok(Value::Symbol(name.clone())),
+        ExprKind::Keyword(name) => Ok(Value::Keyword(Keyword::intern(name))),
+    }
+}
+
+// Splits the items of `(a b . c)` into `a b` and `c`.
+fn split_dotted(items: &[Expr]) -> Result<(&[Expr], Option<&Expr>), Error> {
+    let is_dot = |item: &Expr| matches!(&item.kind, ExprKind::Ident(name) if name == ".");
+    match items.iter().position(is_dot) {
+        None => Ok((items, None)),
+        Some(dot) if dot > 0 && dot + 2 == items.len() => Ok((&items[..dot], Some(&items[dot + 1]))),
+        Some(dot) => Err(Error::syntax("`.` must come between the items of a list and its tail")
+            .at(items[dot].span)),
+    }
+}
+
+fn eval_quoted_list(items: &[Expr]) -> EResult {
+    let (items, tail) = split_dotted(items)?;
+    let tail = tail.map_or(Ok(Value::Nil), eval_quoted)?;
+    Ok(Value::list_with_tail(eval_quoted_items(items)?, tail))
+}
+
+fn eval_quoted_items(items: &[Expr]) -> Result<Vec<Value>, Error> {
+    items.iter().map(eval_quoted).collect()
+}
+
+fn eval_items(items: &[Expr], env: &mut Env) -> Result<Vec<Value>, Error> {
+    items.iter().map(|item| eval_expr(item, env)).collect()
+}
+
+// A map out of alternating keys and values. Literals always have a value for
+// every key, but unquote-splicing into one may leave a key without a value.
+fn map_from(items: Vec<Value>) -> EResult {
+    if items.len() % 2 == 1 {
+        return Err(Error::syntax("map literal with a key but no value"));
+    }
+    Ok(Value::Map(items.into_iter().tuples().collect()))
+}
+
+fn set_from(items: Vec<Value>) -> Value {
+    Value::Set(items.into_iter().collect())
+}
+
+// Like `eval_quoted`, except that unquoted parts are evaluated. `depth` is the
+// number of enclosing quasiquotes minus the number of enclosing unquotes, only
+// unquotes that bring it down to zero are evaluated.
+fn eval_quasiquoted(datum: &Expr, depth: usize, env: &mut Env) -> EResult {
+    let span = datum.span;
+    match &datum.kind {
+        ExprKind::Unquoted(expr) if depth == 1 => eval_expr(expr, env),
+        ExprKind::Unquoted(expr) => Ok(wrap("unquote", eval_quasiquoted(expr, depth - 1, env)?)),
+        ExprKind::UnquoteSpliced(_) if depth == 1 => {
+            Err(Error::syntax("unquote-splicing outside of a list").at(span))
+        }
+        ExprKind::UnquoteSpliced(expr) => Ok(wrap(
+            "unquote-splicing",
+            eval_quasiquoted(expr, depth - 1, env)?,
+        )),
+        ExprKind::Quasiquoted(expr) => Ok(wrap(
+            "quasiquote",
+            eval_quasiquoted(expr, depth + 1, env)?,
+        )),
+        ExprKind::Quoted(expr) => Ok(wrap("quote", eval_quasiquoted(expr, depth, env)?)),
+        ExprKind::List(items) => {
+            let (items, tail) = split_dotted(items)?;
+            let tail = match tail {
+                Some(tail) => eval_quasiquoted(tail, depth, env)?,
+                None => Value::Nil,
+            };
+            Ok(Value::list_with_tail(eval_quasiquoted_items(items, depth, env)?, tail))
+        }
+        ExprKind::Vector(items) => eval_quasiquoted_items(items, depth, env).map(Value::vector),
+        ExprKind::Map(items) => eval_quasiquoted_items(items, depth, env)
+            .and_then(map_from)
+            .map_err(|err| err.at(span)),
+        ExprKind::Set(items) => eval_quasiquoted_items(items, depth, env).map(set_from),
+        _ => eval_quoted(datum),
+    }
+}
+
+// The items of a list, vector, map or set, with unquote-splicing spliced in.
+fn eval_quasiquoted_items(
+    items: &[Expr],
+    depth: usize,
+    env: &mut Env,
+) -> Result<Vec<Value>, Error> {
+    let mut values = Vec::new();
+    for item in items {
+        match &item.kind {
+            ExprKind::UnquoteSpliced(expr) if depth == 1 => {
+                let spliced = eval_expr(expr, env)?;
+                let items = spliced.list_items().ok_or_else(|| {
+                    Error::type_error("list", &spliced)
+                        .in_function("unquote-splicing")
+                        .at(item.span)
+                })?;
+                values.extend(items);
             }
+            _ => values.push(eval_quasiquoted(item, depth, env)?),
         }
     }
+    Ok(values)
+}
+
+// (name datum)
+fn wrap(name: &str, datum: Value) -> Value {
+    Value::list([Value::Symbol(name.into()), datum])
+}
+
+fn eval_list<'a>(body: &'a [Expr], span: Span, env: &mut Env) -> StepResult<'a> {
+    // Errors raised while applying a function called by name are attributed
+    // to that name.
+    let name = match body.first().map(|head| &head.kind) {
+        Some(ExprKind::Ident(name)) => Some(name.as_str()),
+        _ => None,
+    };
+    let attribute = |err: Error| match name {
+        Some(name) => err.in_function(name),
+        None => err,
+    };
+
+    if let Some(special_form) = name.and_then(special_forms::lookup) {
+        return special_form(&body[1..], env).map_err(attribute);
+    }
+    if name.and_then(|name| env.get_macro(name)).is_some() {
+        let form = eval_quoted_list(body)?;
+        let expanded = macroexpand(form, env)?;
+        return Ok(Step::Expanded(expand(value_to_expr(expanded, span)?, env)?, env.clone()));
+    }
+
+    let n = body.len();
+    let mut values = body.iter().map(|item| eval_expr(item, env));
+    let first = match values.next() {
+        Some(first) => first?,
+        None => return Ok(Step::Done(Value::Nil)),
+    };
+    let rest = values.collect::<Result<Vec<_>, _>>()?;
+
+    match first {
+        Value::Fun(_) | Value::Lambda(_) => apply_step(first, rest, env).map_err(attribute),
+        _ if n == 1 => Ok(Step::Done(first)),
+        _ => Err(Error::not_callable(&first)),
+    }
+}
+
+pub(super) fn apply(fun: Value, args: Vec<Value>, env: &mut Env) -> EResult {
+    run(apply_step(fun, args, env)?)
+}
+
+// Applies `fun`, leaving the body of a lambda to the caller.
+fn apply_step(fun: Value, args: Vec<Value>, env: &mut Env) -> StepResult<'static> {
+    match fun {
+        Value::Fun(fun) => fun(args, env).map(Step::Done),
+        Value::Lambda(lambda) => {
+            let arity = match lambda.rest {
+                Some(_) => Arity::AtLeast(lambda.params.len()),
+                None => Arity::Exactly(lambda.params.len()),
+            };
+            if !arity.accepts(args.len()) {
+                return Err(Error::arity(arity, args.len()));
+            }
+
+            let mut scope = lambda.env.extend();
+            let mut args = args.into_iter();
+            for (name, value) in lambda.params.iter().zip(&mut args) {
+                scope.add_binding(name.clone(), value);
+            }
+            if let Some(rest) = &lambda.rest {
+                scope.add_binding(rest.clone(), Value::list(args.collect::<Vec<_>>()));
+            }
+
+            Ok(Step::Call(lambda, scope))
+        }
+        _ => Err(Error::not_callable(&fun)),
+    }
+}
+
+// -------------------------------------------------------------------------- //
+// Macros                                                                     //
+// -------------------------------------------------------------------------- //
+
+// Expands `form` once if it is a call to a macro. Returns `None` otherwise.
+pub(super) fn macroexpand_1(form: &Value, env: &mut Env) -> Result<Option<Value>, Error> {
+    let Value::Pair(pair) = form else {
+        return Ok(None);
+    };
+    let Value::Symbol(name) = &pair.car else {
+        return Ok(None);
+    };
+    let Some(expander) = env.get_macro(name) else {
+        return Ok(None);
+    };
+
+    let args = pair
+        .cdr
+        .list_items()
+        .ok_or_else(|| Error::syntax("macro call must be a proper list"))?;
+    apply(Value::Lambda(expander), args, env)
+        .map(Some)
+        .map_err(|err| err.in_function(name))
+}
+
+// Expands `form` until it no longer is a call to a macro.
+pub(super) fn macroexpand(mut form: Value, env: &mut Env) -> EResult {
+    while let Some(expanded) = macroexpand_1(&form, env)? {
+        form = expanded;
+    }
+    Ok(form)
+}
+
+// Turns data returned by a macro back into code. Every expression gets `span`,
+// the span of the macro call.
+fn value_to_expr(value: Value, span: Span) -> Result<Expr, Error> {
+    let kind = match value {
+        Value::Int(value) => ExprKind::Int(value),
+        Value::Float(value) => ExprKind::Float(value),
+        Value::Ratio(value) => ExprKind::Ratio(value),
+        Value::Str(value) => ExprKind::Str(value),
+        Value::Char(value) => ExprKind::Char(value),
+        Value::Bool(value) => ExprKind::Bool(value),
+        Value::Symbol(name) => ExprKind::Ident(name),
+        Value::Keyword(keyword) => ExprKind::Keyword(keyword.name().to_string()),
+        Value::Nil => ExprKind::List(Vec::new()),
+        Value::Vector(items) => ExprKind::Vector(
+            items
+                .borrow()
+                .iter()
+                .map(|item| value_to_expr(item.clone(), span))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Map(map) => ExprKind::Map(
+            map.into_iter()
+                .flat_map(|(key, value)| [key, value])
+                .map(|item| value_to_expr(item, span))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Set(set) => ExprKind::Set(
+            set.into_iter()
+                .map(|item| value_to_expr(item, span))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Pair(_) => {
+            // The tail of an improper list is written after a `.`
+            let mut items = Vec::new();
+            let mut rest = value;
+            while let Value::Pair(pair) = rest {
+                items.push(value_to_expr(pair.car.clone(), span)?);
+                rest = pair.cdr.clone();
+            }
+            if !matches!(rest, Value::Nil) {
+                items.push(Expr::new(ExprKind::Ident(".".into()), span));
+                items.push(value_to_expr(rest, span)?);
+            }
+
+            // Reader syntax is restored, so that (quasiquote x) works like `x
+            let quoting: Option<fn(Box<Expr>) -> ExprKind> = match &items[..] {
+                [Expr { kind: ExprKind::Ident(name), .. }, _] => match name.as_str() {
+                    "quote" => Some(ExprKind::Quoted),
+                    "quasiquote" => Some(ExprKind::Quasiquoted),
+                    "unquote" => Some(ExprKind::Unquoted),
+                    "unquote-splicing" => Some(ExprKind::UnquoteSpliced),
+                    _ => None,
+                },
+                _ => None,
+            };
+            match quoting {
+                Some(quoting) => quoting(Box::new(items.pop().unwrap())),
+                None => ExprKind::List(items),
+            }
+        }
+        value => {
+            return Err(Error::syntax(format!(
+                "macro expanded into a {}, which is not code",
+                value.type_name()
+            )))
+        }
+    };
+    Ok(Expr::new(kind, span))
 }
 
-fn lookup_indent(name: String, env: &Env) -> EResult {
-    env.get_binding(name).map(Clone::clone).ok_or(())
+fn lookup_indent(name: &str, env: &Env) -> EResult {
+    env.get_binding(name).ok_or_else(|| Error::unbound(name.to_string()))
 }
@@ -0,0 +1,824 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use itertools::Itertools;
+
+use crate::ast::{Expr, ExprKind};
+use crate::span::Span;
+
+use super::{env::Env, error::Error};
+
+// Expansion of `syntax-rules` macros. This runs on the AST of every top-level
+// expression before it is evaluated:
+//
+//   (define-syntax name
+//     (syntax-rules (literal ...)
+//       (pattern template) ...))
+//
+// registers a macro, and every later `(name ...)` form is replaced with the
+// template of the first rule whose pattern matches it. Patterns support
+// literals, `_`, nested lists, `x ...` repetitions and `. rest` tails.
+//
+// Expansion is hygienic: identifiers introduced by a template can't capture
+// identifiers passed in by the user, and the other way around.
+// - An introduced identifier that the template binds (as a lambda parameter, a
+//   let binding, ...) is renamed to a fresh `name%n` within the scope of that
+//   binding.
+// - Introduced identifiers that are free, like `if` or `+`, refer to the
+//   top-level environment, where syntax is defined. They keep their name,
+//   unless a local binding around them shadows a top-level binding of that
+//   name: they then become `(%global name)`.
+
+const ELLIPSIS: &str = "...";
+
+pub struct SyntaxRules {
+    literals: Vec<String>,
+    rules: Vec<(Expr, Expr)>,
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+// Expands every use of a syntax-rules macro in `expr`, registering any
+// `define-syntax` forms it contains in `env` along the way.
+pub(super) fn expand(expr: Expr, env: &Env) -> Result<Expr> {
+    Expander {
+        env,
+        bound: Vec::new(),
+    }
+    .expand(expr)
+}
+
+struct Expander<'a> {
+    env: &'a Env,
+    // Local variables in scope. They shadow macros of the same name.
+    bound: Vec<String>,
+}
+
+impl Expander<'_> {
+    fn expand(&mut self, expr: Expr) -> Result<Expr> {
+        let span = expr.span;
+        let kind = match expr.kind {
+            ExprKind::List(items) => return self.expand_list(items, span),
+            ExprKind::Vector(items) => ExprKind::Vector(self.expand_items(items)?),
+            ExprKind::Map(items) => ExprKind::Map(self.expand_items(items)?),
+            ExprKind::Set(items) => ExprKind::Set(self.expand_items(items)?),
+            ExprKind::Quoted(datum) => ExprKind::Quoted(datum),
+            ExprKind::Quasiquoted(datum) => {
+                ExprKind::Quasiquoted(self.expand_quasiquoted(*datum, 1)?)
+            }
+            ExprKind::Unquoted(expr) => ExprKind::Unquoted(Box::new(self.expand(*expr)?)),
+            ExprKind::UnquoteSpliced(expr) => {
+                ExprKind::UnquoteSpliced(Box::new(self.expand(*expr)?))
+            }
+            kind => kind,
+        };
+        Ok(Expr::new(kind, span))
+    }
+
+    fn expand_list(&mut self, items: Vec<Expr>, span: Span) -> Result<Expr> {
+        let head = match items.first().map(|head| &head.kind) {
+            Some(ExprKind::Ident(name)) => name.clone(),
+            _ => return self.expand_all(items, span),
+        };
+
+        match head.as_str() {
+            "define-syntax" => {
+                self.define_syntax(items, span)?;
+                // Nothing is left to evaluate
+                Ok(Expr::new(ExprKind::List(vec![ident("begin", span)]), span))
+            }
+            "quote" => Ok(Expr::new(ExprKind::List(items), span)),
+            "lambda" | "fn" | "defmacro" => {
+                // Everything up to and including the parameter list is kept
+                let kept = if head == "defmacro" { 3 } else { 2 };
+                let params = items.get(kept - 1).map(parameter_names).unwrap_or_default();
+                self.expand_scoped(items, kept, params, span)
+            }
+            "let" | "let*" | "letrec" if items.len() > 1 => {
+                let mut items = items.into_iter();
+                let keyword = items.next().unwrap();
+                let bindings = items.next().unwrap();
+
+                let names = binding_names(&bindings);
+                let bindings_span = bindings.span;
+                let bindings = match bindings.kind {
+                    ExprKind::List(bindings) => {
+                        let scope = if head == "let" {
+                            Vec::new()
+                        } else {
+                            names.clone()
+                        };
+                        let bindings = self.with_bound(scope, |this| {
+                            bindings
+                                .into_iter()
+                                .map(|binding| this.expand_binding(binding))
+                                .collect::<Result<Vec<_>>>()
+                        })?;
+                        Expr::new(ExprKind::List(bindings), bindings_span)
+                    }
+                    _ => bindings,
+                };
+
+                let mut expanded = vec![keyword, bindings];
+                expanded.extend(self.with_bound(names, |this| {
+                    items
+                        .map(|item| this.expand(item))
+                        .collect::<Result<Vec<_>>>()
+                })?);
+                Ok(Expr::new(ExprKind::List(expanded), span))
+            }
+            name if !self.bound.iter().any(|bound| bound == name) => {
+                match self.env.get_syntax(name) {
+                    Some(rules) => {
+                        let form = Expr::new(ExprKind::List(items), span);
+                        let expanded = rules.apply(&form, name, &self.bound, self.env)?;
+                        self.expand(expanded)
+                    }
+                    None => self.expand_all(items, span),
+                }
+            }
+            _ => self.expand_all(items, span),
+        }
+    }
+
+    // `(name init)`, the name is left alone.
+    fn expand_binding(&mut self, binding: Expr) -> Result<Expr> {
+        match binding.kind {
+            ExprKind::List(mut pair) if pair.len() == 2 => {
+                let init = pair.pop().unwrap();
+                pair.push(self.expand(init)?);
+                Ok(Expr::new(ExprKind::List(pair), binding.span))
+            }
+            _ => Ok(binding),
+        }
+    }
+
+    // Keeps the first `kept` items as they are and expands the rest with
+    // `names` in scope.
+    fn expand_scoped(
+        &mut self,
+        items: Vec<Expr>,
+        kept: usize,
+        names: Vec<String>,
+        span: Span,
+    ) -> Result<Expr> {
+        let mut items = items.into_iter();
+        let mut expanded: Vec<_> = items.by_ref().take(kept).collect();
+        expanded.extend(self.with_bound(names, |this| {
+            items
+                .map(|item| this.expand(item))
+                .collect::<Result<Vec<_>>>()
+        })?);
+        Ok(Expr::new(ExprKind::List(expanded), span))
+    }
+
+    fn expand_all(&mut self, items: Vec<Expr>, span: Span) -> Result<Expr> {
+        let items = items
+            .into_iter()
+            .map(|item| self.expand(item))
+            .collect::<Result<_>>()?;
+        Ok(Expr::new(ExprKind::List(items), span))
+    }
+
+    // Only unquoted parts of a quasiquote are code.
+    fn expand_quasiquoted(&mut self, datum: Expr, depth: usize) -> Result<Box<Expr>> {
+        let span = datum.span;
+        let kind = match datum.kind {
+            ExprKind::Unquoted(expr) if depth == 1 => {
+                ExprKind::Unquoted(Box::new(self.expand(*expr)?))
+            }
+            ExprKind::UnquoteSpliced(expr) if depth == 1 => {
+                ExprKind::UnquoteSpliced(Box::new(self.expand(*expr)?))
+            }
+            ExprKind::Unquoted(expr) => {
+                ExprKind::Unquoted(self.expand_quasiquoted(*expr, depth - 1)?)
+            }
+            ExprKind::UnquoteSpliced(expr) => {
+                ExprKind::UnquoteSpliced(self.expand_quasiquoted(*expr, depth - 1)?)
+            }
+            ExprKind::Quasiquoted(expr) => {
+                ExprKind::Quasiquoted(self.expand_quasiquoted(*expr, depth + 1)?)
+            }
+            ExprKind::Quoted(expr) => ExprKind::Quoted(self.expand_quasiquoted(*expr, depth)?),
+            ExprKind::List(items) => ExprKind::List(self.expand_quasiquoted_items(items, depth)?),
+            ExprKind::Vector(items) => {
+                ExprKind::Vector(self.expand_quasiquoted_items(items, depth)?)
+            }
+            ExprKind::Map(items) => ExprKind::Map(self.expand_quasiquoted_items(items, depth)?),
+            ExprKind::Set(items) => ExprKind::Set(self.expand_quasiquoted_items(items, depth)?),
+            kind => kind,
+        };
+        Ok(Box::new(Expr::new(kind, span)))
+    }
+
+    fn expand_quasiquoted_items(&mut self, items: Vec<Expr>, depth: usize) -> Result<Vec<Expr>> {
+        items
+            .into_iter()
+            .map(|item| self.expand_quasiquoted(item, depth).map(|item| *item))
+            .collect()
+    }
+
+    fn expand_items(&mut self, items: Vec<Expr>) -> Result<Vec<Expr>> {
+        items.into_iter().map(|item| self.expand(item)).collect()
+    }
+
+    fn with_bound<T>(&mut self, names: Vec<String>, f: impl FnOnce(&mut Self) -> T) -> T {
+        let len = self.bound.len();
+        self.bound.extend(names);
+        let result = f(self);
+        self.bound.truncate(len);
+        result
+    }
+
+    // (define-syntax name (syntax-rules (literal ...) (pattern template) ...))
+    fn define_syntax(&mut self, items: Vec<Expr>, span: Span) -> Result<()> {
+        let invalid = |reason: &str| Error::syntax(reason).in_function("define-syntax").at(span);
+
+        let [_, name, rules] = <[Expr; 3]>::try_from(items)
+            .map_err(|_| invalid("expected (define-syntax name (syntax-rules ...))"))?;
+        let ExprKind::Ident(name) = name.kind else {
+            return Err(invalid("expected a macro name"));
+        };
+        let ExprKind::List(rules) = rules.kind else {
+            return Err(invalid("expected a syntax-rules form"));
+        };
+
+        let mut rules = rules.into_iter();
+        match rules.next().map(|head| head.kind) {
+            Some(ExprKind::Ident(head)) if head == "syntax-rules" => {}
+            _ => return Err(invalid("expected a syntax-rules form")),
+        }
+        let literals = match rules.next().map(|literals| literals.kind) {
+            Some(ExprKind::List(literals)) => literals
+                .into_iter()
+                .map(|literal| match literal.kind {
+                    ExprKind::Ident(literal) => Ok(literal),
+                    _ => Err(invalid("literals must be identifiers")),
+                })
+                .collect::<Result<Vec<_>>>()?,
+            _ => return Err(invalid("expected a list of literals")),
+        };
+        let rules = rules
+            .map(|rule| match rule.kind {
+                ExprKind::List(rule) => match <[Expr; 2]>::try_from(rule) {
+                    Ok([pattern, template]) => match &pattern.kind {
+                        // The first element stands for the keyword, `apply`
+                        // skips it
+                        ExprKind::List(patterns) if patterns.is_empty() => {
+                            Err(invalid("a pattern must start with the macro keyword"))
+                        }
+                        ExprKind::List(patterns) => {
+                            check_ellipses(&patterns[1..]).map_err(invalid)?;
+                            Ok((pattern, template))
+                        }
+                        _ => Err(invalid("rules must have the form ((pattern ...) template)")),
+                    },
+                    _ => Err(invalid("rules must have the form ((pattern ...) template)")),
+                },
+                _ => Err(invalid("rules must have the form ((pattern ...) template)")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.env
+            .define_syntax(name, SyntaxRules { literals, rules });
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------- //
+// Pattern matching                                                           //
+// -------------------------------------------------------------------------- //
+
+// What a pattern variable matched. Variables under `n` ellipses are nested
+// `n` levels of `Many` deep.
+#[derive(Clone)]
+enum Binding {
+    One(Expr),
+    Many(Vec<Binding>),
+}
+
+type Bindings = HashMap<String, Binding>;
+
+impl SyntaxRules {
+    // `bound` are the local variables in scope where the macro is used.
+    fn apply(&self, form: &Expr, name: &str, bound: &[String], env: &Env) -> Result<Expr> {
+        for (pattern, template) in &self.rules {
+            let mut bindings = Bindings::new();
+            // The macro keyword itself is never matched
+            let (ExprKind::List(pattern), ExprKind::List(items)) = (&pattern.kind, &form.kind)
+            else {
+                unreachable!()
+            };
+            if self.match_list(&pattern[1..], &items[1..], form.span, &mut bindings) {
+                let mut renamer = Renamer::new(form.span);
+                let expanded = renamer.transcribe(template, &bindings)?;
+                let mut resolver = Resolver {
+                    suffix: &renamer.suffix,
+                    env,
+                    scope: bound.to_vec(),
+                };
+                return Ok(resolver.code(expanded));
+            }
+        }
+
+        Err(Error::syntax("no syntax rule matches")
+            .in_function(name)
+            .at(form.span))
+    }
+
+    fn match_pattern(&self, pattern: &Expr, form: &Expr, bindings: &mut Bindings) -> bool {
+        match &pattern.kind {
+            ExprKind::Ident(name) if name == "_" => true,
+            ExprKind::Ident(name) if self.literals.contains(name) => {
+                matches!(&form.kind, ExprKind::Ident(other) if other == name)
+            }
+            ExprKind::Ident(name) => {
+                bindings.insert(name.clone(), Binding::One(form.clone()));
+                true
+            }
+            ExprKind::List(patterns) => match &form.kind {
+                ExprKind::List(items) => self.match_list(patterns, items, form.span, bindings),
+                _ => false,
+            },
+            _ => pattern == form,
+        }
+    }
+
+    fn match_list(
+        &self,
+        patterns: &[Expr],
+        items: &[Expr],
+        span: Span,
+        bindings: &mut Bindings,
+    ) -> bool {
+        let (patterns, tail) = split_tail(patterns);
+
+        let Some(ellipsis) = patterns.iter().position(is_ellipsis) else {
+            let fixed = match tail {
+                Some(_) => items.len() >= patterns.len(),
+                None => items.len() == patterns.len(),
+            };
+            return fixed
+                && self.match_each(patterns, &items[..patterns.len()], bindings)
+                && self.match_tail(tail, &items[patterns.len()..], span, bindings);
+        };
+
+        // (before ... repeated ... after ...)
+        let (before, after) = (&patterns[..ellipsis - 1], &patterns[ellipsis + 1..]);
+        let repeated = &patterns[ellipsis - 1];
+        let Some(count) = items.len().checked_sub(before.len() + after.len()) else {
+            return false;
+        };
+        let (matched_before, rest) = items.split_at(before.len());
+        let (matched_repeated, matched_after) = rest.split_at(count);
+
+        if !self.match_each(before, matched_before, bindings)
+            || !self.match_each(after, matched_after, bindings)
+        {
+            return false;
+        }
+
+        let mut repetitions = Vec::new();
+        for item in matched_repeated {
+            let mut inner = Bindings::new();
+            if !self.match_pattern(repeated, item, &mut inner) {
+                return false;
+            }
+            repetitions.push(inner);
+        }
+        for var in self.pattern_vars(repeated) {
+            let matched = repetitions
+                .iter_mut()
+                .map(|inner| inner.remove(&var).unwrap())
+                .collect();
+            bindings.insert(var, Binding::Many(matched));
+        }
+        self.match_tail(tail, &[], span, bindings)
+    }
+
+    fn match_each(&self, patterns: &[Expr], items: &[Expr], bindings: &mut Bindings) -> bool {
+        patterns
+            .iter()
+            .zip(items)
+            .all(|(pattern, item)| self.match_pattern(pattern, item, bindings))
+    }
+
+    fn match_tail(
+        &self,
+        tail: Option<&Expr>,
+        rest: &[Expr],
+        span: Span,
+        bindings: &mut Bindings,
+    ) -> bool {
+        match tail {
+            Some(tail) => self.match_pattern(
+                tail,
+                &Expr::new(ExprKind::List(rest.to_vec()), span),
+                bindings,
+            ),
+            None => true,
+        }
+    }
+
+    fn pattern_vars(&self, pattern: &Expr) -> Vec<String> {
+        match &pattern.kind {
+            ExprKind::Ident(name) if name == "_" || name == "." || name == ELLIPSIS => Vec::new(),
+            ExprKind::Ident(name) if self.literals.contains(name) => Vec::new(),
+            ExprKind::Ident(name) => vec![name.clone()],
+            ExprKind::List(patterns) => patterns
+                .iter()
+                .flat_map(|pattern| self.pattern_vars(pattern))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------- //
+// Transcription and renaming                                                 //
+// -------------------------------------------------------------------------- //
+
+static EXPANSIONS: AtomicUsize = AtomicUsize::new(0);
+
+// Fills in a template. Every identifier the template introduces is renamed to
+// `name%n`, `n` being unique to the expansion; `Resolver` then restores the
+// original name of those that aren't in the scope of a binding of `name%n`.
+struct Renamer {
+    suffix: String,
+    span: Span,
+}
+
+impl Renamer {
+    fn new(span: Span) -> Self {
+        let n = EXPANSIONS.fetch_add(1, Ordering::Relaxed);
+        Self {
+            suffix: format!("%{n}"),
+            span,
+        }
+    }
+
+    fn transcribe(&mut self, template: &Expr, bindings: &Bindings) -> Result<Expr> {
+        let kind = match &template.kind {
+            ExprKind::Ident(name) => match bindings.get(name) {
+                Some(Binding::One(expr)) => return Ok(expr.clone()),
+                Some(Binding::Many(_)) => {
+                    return Err(self.error(format!("`{name}` must be followed by `...`")));
+                }
+                None if name == "." => ExprKind::Ident(name.clone()),
+                None => ExprKind::Ident(format!("{name}{}", self.suffix)),
+            },
+            ExprKind::List(templates) => ExprKind::List(self.transcribe_list(templates, bindings)?),
+            ExprKind::Vector(templates) => {
+                ExprKind::Vector(self.transcribe_list(templates, bindings)?)
+            }
+            ExprKind::Map(templates) => ExprKind::Map(self.transcribe_list(templates, bindings)?),
+            ExprKind::Set(templates) => ExprKind::Set(self.transcribe_list(templates, bindings)?),
+            ExprKind::Quoted(datum) => {
+                ExprKind::Quoted(Box::new(self.transcribe(datum, bindings)?))
+            }
+            ExprKind::Quasiquoted(datum) => {
+                ExprKind::Quasiquoted(Box::new(self.transcribe(datum, bindings)?))
+            }
+            ExprKind::Unquoted(datum) => {
+                ExprKind::Unquoted(Box::new(self.transcribe(datum, bindings)?))
+            }
+            ExprKind::UnquoteSpliced(datum) => {
+                ExprKind::UnquoteSpliced(Box::new(self.transcribe(datum, bindings)?))
+            }
+            kind => kind.clone(),
+        };
+        Ok(Expr::new(kind, self.span))
+    }
+
+    fn transcribe_list(&mut self, templates: &[Expr], bindings: &Bindings) -> Result<Vec<Expr>> {
+        let (templates, tail) = split_tail(templates);
+
+        let mut items = Vec::new();
+        let mut templates = templates.iter().peekable();
+        while let Some(template) = templates.next() {
+            if templates.next_if(|next| is_ellipsis(next)).is_none() {
+                items.push(self.transcribe(template, bindings)?);
+                continue;
+            }
+
+            // template ...: once for every match of the variables under it
+            let vars: Vec<_> = template_idents(template)
+                .into_iter()
+                .filter(|var| matches!(bindings.get(var), Some(Binding::Many(_))))
+                .collect();
+            let counts: HashSet<_> = vars
+                .iter()
+                .map(|var| match &bindings[var] {
+                    Binding::Many(matched) => matched.len(),
+                    Binding::One(_) => unreachable!(),
+                })
+                .collect();
+            let count = match counts.len() {
+                1 => counts.into_iter().next().unwrap(),
+                0 => return Err(self.error("`...` must follow a repeated pattern variable")),
+                _ => {
+                    return Err(self.error("pattern variables under `...` matched different counts"))
+                }
+            };
+
+            for i in 0..count {
+                let mut inner = bindings.clone();
+                for var in &vars {
+                    if let Binding::Many(matched) = &bindings[var] {
+                        inner.insert(var.clone(), matched[i].clone());
+                    }
+                }
+                items.push(self.transcribe(template, &inner)?);
+            }
+        }
+
+        // (a . tail) is spliced into the list, tail has to be one
+        if let Some(tail) = tail {
+            match self.transcribe(tail, bindings)?.kind {
+                ExprKind::List(tail) => items.extend(tail),
+                _ => return Err(self.error("the tail of a template must be a list")),
+            }
+        }
+        Ok(items)
+    }
+
+    fn error(&self, reason: impl Into<String>) -> Error {
+        Error::syntax(reason).at(self.span)
+    }
+}
+
+struct Resolver<'a> {
+    suffix: &'a str,
+    env: &'a Env,
+    // The names bound around the expression being resolved, by the template
+    // (renamed) or by the user (as they are).
+    scope: Vec<String>,
+}
+
+impl Resolver<'_> {
+    fn code(&mut self, expr: Expr) -> Expr {
+        let span = expr.span;
+        let kind = match expr.kind {
+            ExprKind::Ident(name) => return self.ident(name, span),
+            ExprKind::List(items) => ExprKind::List(self.list(items)),
+            ExprKind::Vector(items) => ExprKind::Vector(self.code_items(items)),
+            ExprKind::Map(items) => ExprKind::Map(self.code_items(items)),
+            ExprKind::Set(items) => ExprKind::Set(self.code_items(items)),
+            ExprKind::Quoted(datum) => ExprKind::Quoted(Box::new(self.datum(*datum))),
+            ExprKind::Quasiquoted(datum) => {
+                ExprKind::Quasiquoted(Box::new(self.quasiquoted(*datum, 1)))
+            }
+            ExprKind::Unquoted(expr) => ExprKind::Unquoted(Box::new(self.code(*expr))),
+            ExprKind::UnquoteSpliced(expr) => {
+                ExprKind::UnquoteSpliced(Box::new(self.code(*expr)))
+            }
+            kind => kind,
+        };
+        Expr::new(kind, span)
+    }
+
+    fn ident(&self, name: String, span: Span) -> Expr {
+        let original = match name.strip_suffix(self.suffix) {
+            Some(original) if !self.scope.contains(&name) => original,
+            _ => return Expr::new(ExprKind::Ident(name), span),
+        };
+        if self.scope.iter().any(|bound| bound == original)
+            && self.env.get_global(original).is_some()
+        {
+            let global = vec![ident("%global", span), ident(original, span)];
+            Expr::new(ExprKind::List(global), span)
+        } else {
+            ident(original, span)
+        }
+    }
+
+    // Binders are left as they are, and are in scope of the code that follows
+    // them.
+    fn list(&mut self, items: Vec<Expr>) -> Vec<Expr> {
+        let head = match items.first().map(|head| &head.kind) {
+            // The keyword itself may have been introduced by the template
+            Some(ExprKind::Ident(head)) => {
+                head.strip_suffix(self.suffix).unwrap_or(head).to_string()
+            }
+            _ => return self.code_items(items),
+        };
+        let len = self.scope.len();
+        let mut items = items.into_iter();
+        let mut resolved = Vec::new();
+        match head.as_str() {
+            "quote" => {
+                resolved.push(self.code(items.next().unwrap()));
+                resolved.extend(items.map(|datum| self.datum(datum)));
+                return resolved;
+            }
+            "lambda" | "fn" if items.len() > 1 => {
+                resolved.push(self.code(items.next().unwrap()));
+                let params = items.next().unwrap();
+                self.scope.extend(parameter_names(&params));
+                resolved.push(params);
+            }
+            "defmacro" if items.len() > 2 => {
+                resolved.push(self.code(items.next().unwrap()));
+                resolved.push(self.datum(items.next().unwrap()));
+                let params = items.next().unwrap();
+                self.scope.extend(parameter_names(&params));
+                resolved.push(params);
+            }
+            "let" | "let*" | "letrec" if items.len() > 1 => {
+                resolved.push(self.code(items.next().unwrap()));
+                if head == "letrec" {
+                    self.scope.extend(binding_names(&items.as_slice()[0]));
+                }
+                let bindings = items.next().unwrap();
+                let names = binding_names(&bindings);
+                let span = bindings.span;
+                let kind = match bindings.kind {
+                    ExprKind::List(bindings) => ExprKind::List(
+                        bindings
+                            .into_iter()
+                            .map(|binding| self.binding(binding, head == "let*"))
+                            .collect(),
+                    ),
+                    kind => kind,
+                };
+                resolved.push(Expr::new(kind, span));
+                self.scope.extend(names);
+            }
+            _ => return self.code_items(items.collect()),
+        }
+        resolved.extend(items.map(|item| self.code(item)));
+        self.scope.truncate(len);
+        resolved
+    }
+
+    // `(name init)`. With `let*`, the name is in scope of the bindings that
+    // follow.
+    fn binding(&mut self, binding: Expr, sequential: bool) -> Expr {
+        let ExprKind::List(mut pair) = binding.kind else {
+            return binding;
+        };
+        if pair.len() == 2 {
+            let init = pair.pop().unwrap();
+            pair.push(self.code(init));
+            if let (true, ExprKind::Ident(name)) = (sequential, &pair[0].kind) {
+                self.scope.push(name.clone());
+            }
+        }
+        Expr::new(ExprKind::List(pair), binding.span)
+    }
+
+    fn code_items(&mut self, items: Vec<Expr>) -> Vec<Expr> {
+        items.into_iter().map(|item| self.code(item)).collect()
+    }
+
+    // Quoted data keeps the names written in the template.
+    fn datum(&self, datum: Expr) -> Expr {
+        let span = datum.span;
+        let kind = match datum.kind {
+            ExprKind::Ident(name) => match name.strip_suffix(self.suffix) {
+                Some(original) => ExprKind::Ident(original.to_string()),
+                None => ExprKind::Ident(name),
+            },
+            ExprKind::List(items) => ExprKind::List(self.data(items)),
+            ExprKind::Vector(items) => ExprKind::Vector(self.data(items)),
+            ExprKind::Map(items) => ExprKind::Map(self.data(items)),
+            ExprKind::Set(items) => ExprKind::Set(self.data(items)),
+            ExprKind::Quoted(datum) => ExprKind::Quoted(Box::new(self.datum(*datum))),
+            ExprKind::Quasiquoted(datum) => ExprKind::Quasiquoted(Box::new(self.datum(*datum))),
+            ExprKind::Unquoted(datum) => ExprKind::Unquoted(Box::new(self.datum(*datum))),
+            ExprKind::UnquoteSpliced(datum) => {
+                ExprKind::UnquoteSpliced(Box::new(self.datum(*datum)))
+            }
+            kind => kind,
+        };
+        Expr::new(kind, span)
+    }
+
+    fn data(&self, items: Vec<Expr>) -> Vec<Expr> {
+        items.into_iter().map(|item| self.datum(item)).collect()
+    }
+
+    // Data, except for the parts that unquotes bring back to depth zero.
+    fn quasiquoted(&mut self, datum: Expr, depth: usize) -> Expr {
+        let span = datum.span;
+        let kind = match datum.kind {
+            ExprKind::Unquoted(expr) if depth == 1 => {
+                ExprKind::Unquoted(Box::new(self.code(*expr)))
+            }
+            ExprKind::UnquoteSpliced(expr) if depth == 1 => {
+                ExprKind::UnquoteSpliced(Box::new(self.code(*expr)))
+            }
+            ExprKind::Unquoted(expr) => {
+                ExprKind::Unquoted(Box::new(self.quasiquoted(*expr, depth - 1)))
+            }
+            ExprKind::UnquoteSpliced(expr) => {
+                ExprKind::UnquoteSpliced(Box::new(self.quasiquoted(*expr, depth - 1)))
+            }
+            ExprKind::Quasiquoted(expr) => {
+                ExprKind::Quasiquoted(Box::new(self.quasiquoted(*expr, depth + 1)))
+            }
+            ExprKind::Quoted(expr) => ExprKind::Quoted(Box::new(self.quasiquoted(*expr, depth))),
+            ExprKind::List(items) => ExprKind::List(self.quasiquoted_items(items, depth)),
+            ExprKind::Vector(items) => ExprKind::Vector(self.quasiquoted_items(items, depth)),
+            ExprKind::Map(items) => ExprKind::Map(self.quasiquoted_items(items, depth)),
+            ExprKind::Set(items) => ExprKind::Set(self.quasiquoted_items(items, depth)),
+            kind => return self.datum(Expr::new(kind, span)),
+        };
+        Expr::new(kind, span)
+    }
+
+    fn quasiquoted_items(&mut self, items: Vec<Expr>, depth: usize) -> Vec<Expr> {
+        items
+            .into_iter()
+            .map(|item| self.quasiquoted(item, depth))
+            .collect()
+    }
+}
+
+// -------------------------------------------------------------------------- //
+// Helpers                                                                    //
+// -------------------------------------------------------------------------- //
+
+
+fn parameter_names(params: &Expr) -> Vec<String> {
+    match &params.kind {
+        ExprKind::Ident(name) => vec![name.clone()],
+        ExprKind::List(params) => params
+            .iter()
+            .filter_map(|param| match &param.kind {
+                ExprKind::Ident(name) if name != "." => Some(name.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn binding_names(bindings: &Expr) -> Vec<String> {
+    let ExprKind::List(bindings) = &bindings.kind else {
+        return Vec::new();
+    };
+    bindings
+        .iter()
+        .filter_map(|binding| match &binding.kind {
+            ExprKind::List(pair) => match pair.first().map(|name| &name.kind) {
+                Some(ExprKind::Ident(name)) => Some(name.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+// Every `...` in a pattern has to follow the pattern it repeats, and there can
+// be only one of them per list.
+fn check_ellipses(patterns: &[Expr]) -> std::result::Result<(), &'static str> {
+    let (patterns, tail) = split_tail(patterns);
+    match patterns.iter().positions(is_ellipsis).collect::<Vec<_>>()[..] {
+        [] => {}
+        [0] => return Err("`...` must follow the pattern it repeats"),
+        [_] => {}
+        _ => return Err("a list pattern can only contain one `...`"),
+    }
+    for pattern in patterns.iter().chain(tail) {
+        if let ExprKind::List(items) = &pattern.kind {
+            check_ellipses(items)?;
+        }
+    }
+    Ok(())
+}
+
+fn template_idents(template: &Expr) -> Vec<String> {
+    match &template.kind {
+        ExprKind::Ident(name) => vec![name.clone()],
+        ExprKind::List(items)
+        | ExprKind::Vector(items)
+        | ExprKind::Map(items)
+        | ExprKind::Set(items) => items.iter().flat_map(template_idents).collect(),
+        ExprKind::Quoted(datum)
+        | ExprKind::Quasiquoted(datum)
+        | ExprKind::Unquoted(datum)
+        | ExprKind::UnquoteSpliced(datum) => template_idents(datum),
+        _ => Vec::new(),
+    }
+}
+
+// Splits `(a b . c)` into `(a b)` and `c`.
+fn split_tail(items: &[Expr]) -> (&[Expr], Option<&Expr>) {
+    match items {
+        [init @ .., dot, tail] if matches!(&dot.kind, ExprKind::Ident(name) if name == ".") => {
+            (init, Some(tail))
+        }
+        _ => (items, None),
+    }
+}
+
+fn is_ellipsis(expr: &Expr) -> bool {
+    matches!(&expr.kind, ExprKind::Ident(name) if name == ELLIPSIS)
+}
+
+fn ident(name: &str, span: Span) -> Expr {
+    Expr::new(ExprKind::Ident(name.into()), span)
+}
@@ -0,0 +1,64 @@
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+thread_local! {
+    // Every keyword that has been read so far. Keywords are never freed, there
+    // can only be as many of them as the program spells out.
+    static KEYWORDS: RefCell<HashSet<Rc<str>>> = RefCell::new(HashSet::new());
+}
+
+// An interned `:name`. There is only ever one keyword with a given name, so
+// comparing keywords works on pointers, not on names. Hashing uses the name,
+// so that maps and sets of keywords print in the same order on every run.
+#[derive(Clone)]
+pub struct Keyword(Rc<str>);
+
+impl Keyword {
+    pub fn intern(name: &str) -> Self {
+        KEYWORDS.with(|keywords| {
+            let mut keywords = keywords.borrow_mut();
+            if let Some(name) = keywords.get(name) {
+                return Self(name.clone());
+            }
+            let name: Rc<str> = name.into();
+            keywords.insert(name.clone());
+            Self(name)
+        })
+    }
+
+    // The name, without the colon.
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Keyword {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Keyword {}
+
+impl Hash for Keyword {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl fmt::Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ":{}", self.0)
+    }
+}
+
+impl fmt::Debug for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ":{}", self.0)
+    }
+}
@@ -1,5 +1,11 @@
+pub mod env;
+pub mod error;
 pub mod evaluator;
+pub mod keyword;
 pub mod value;
 
 mod builtins;
-mod env;
+mod expander;
+mod special_forms;
+
+pub use error::Error;
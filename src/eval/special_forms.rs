@@ -0,0 +1,316 @@
+use std::rc::Rc;
+
+use crate::ast::{Expr, ExprKind};
+
+use super::{
+    env::Env,
+    error::{Arity, Error},
+    evaluator::{eval_expr, eval_quoted, Step, StepResult},
+    value::{Lambda, Value},
+};
+
+// Special forms receive their arguments unevaluated and decide for themselves
+// what (and whether) to evaluate. The expression in tail position is handed
+// back to the evaluator rather than evaluated, see `Step`.
+pub(super) type SpecialForm = for<'a> fn(&'a [Expr], &mut Env) -> StepResult<'a>;
+
+#[rustfmt::skip]
+pub(super) fn lookup(name: &str) -> Option<SpecialForm> {
+    Some(match name {
+        "quote"            => quote,
+        "if"               => if_,
+        "cond"             => cond,
+        "when"             => when,
+        "unless"           => unless,
+        "and"              => and,
+        "or"               => or,
+        "let"              => let_,
+        "let*"             => let_star,
+        "letrec"           => letrec,
+        "begin"            => begin,
+        "set!"             => set,
+        "lambda" | "fn"    => lambda,
+        "defmacro"         => defmacro,
+        "%global"          => global,
+        _ => return None,
+    })
+}
+
+// (quote datum)
+fn quote<'a>(args: &'a [Expr], _: &mut Env) -> StepResult<'a> {
+    let [datum] = exactly::<1>(args)?;
+    eval_quoted(datum).map(Step::Done)
+}
+
+// (if test consequent [alternative])
+fn if_<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    if !matches!(args.len(), 2 | 3) {
+        return Err(Error::arity(Arity::Between(2, 3), args.len()));
+    }
+
+    let test = eval_expr(&args[0], env)?;
+    let consequent = &args[1];
+    let alternative = args.get(2);
+
+    if test.is_truthy() {
+        Ok(Step::Eval(consequent, env.clone()))
+    } else {
+        Ok(alternative.map_or(Step::Done(Value::None), |alt| Step::Eval(alt, env.clone())))
+    }
+}
+
+// (cond (test body ...) ... [(else body ...)])
+fn cond<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    for clause in args {
+        let (test, body) = match &clause.kind {
+            ExprKind::List(items) if !items.is_empty() => (&items[0], &items[1..]),
+            _ => {
+                return Err(Error::syntax("cond clauses must be non-empty lists").at(clause.span))
+            }
+        };
+
+        let test = match &test.kind {
+            ExprKind::Ident(name) if name == "else" => Value::Bool(true),
+            _ => eval_expr(test, env)?,
+        };
+
+        if test.is_truthy() {
+            return if body.is_empty() { Ok(Step::Done(test)) } else { eval_body(body, env) };
+        }
+    }
+
+    Ok(Step::Done(Value::None))
+}
+
+// (when test body ...)
+fn when<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    let (test, body) = split_first(args)?;
+    if eval_expr(test, env)?.is_truthy() {
+        eval_body(body, env)
+    } else {
+        Ok(Step::Done(Value::None))
+    }
+}
+
+// (unless test body ...)
+fn unless<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    let (test, body) = split_first(args)?;
+    if eval_expr(test, env)?.is_truthy() {
+        Ok(Step::Done(Value::None))
+    } else {
+        eval_body(body, env)
+    }
+}
+
+// (and expr ...)
+fn and<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    let Some((last, args)) = args.split_last() else {
+        return Ok(Step::Done(Value::Bool(true)));
+    };
+    for expr in args {
+        let value = eval_expr(expr, env)?;
+        if !value.is_truthy() {
+            return Ok(Step::Done(value));
+        }
+    }
+    Ok(Step::Eval(last, env.clone()))
+}
+
+// (or expr ...)
+fn or<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    let Some((last, args)) = args.split_last() else {
+        return Ok(Step::Done(Value::Bool(false)));
+    };
+    for expr in args {
+        let value = eval_expr(expr, env)?;
+        if value.is_truthy() {
+            return Ok(Step::Done(value));
+        }
+    }
+    Ok(Step::Eval(last, env.clone()))
+}
+
+// (let ((name init) ...) body ...)
+// All inits are evaluated in the outer environment.
+fn let_<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    let (bindings, body) = split_first(args)?;
+    let bindings = parse_bindings(bindings)?;
+
+    let values = bindings
+        .into_iter()
+        .map(|(name, init)| Ok((name, eval_expr(init, env)?)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut scope = env.extend();
+    for (name, value) in values {
+        scope.add_binding(name, value);
+    }
+    eval_body(body, &mut scope)
+}
+
+// (let* ((name init) ...) body ...)
+// Each init sees the bindings that precede it.
+fn let_star<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    let (bindings, body) = split_first(args)?;
+    let bindings = parse_bindings(bindings)?;
+
+    let mut scope = env.clone();
+    for (name, init) in bindings {
+        let value = eval_expr(init, &mut scope)?;
+        scope = scope.extend();
+        scope.add_binding(name, value);
+    }
+    eval_body(body, &mut scope)
+}
+
+// (letrec ((name init) ...) body ...)
+// Every init is evaluated in the new scope, so lambdas can refer to each other.
+fn letrec<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    let (bindings, body) = split_first(args)?;
+    let bindings = parse_bindings(bindings)?;
+
+    let mut scope = env.extend();
+    for (name, init) in bindings {
+        let value = eval_expr(init, &mut scope)?;
+        scope.add_binding(name, value);
+    }
+    eval_body(body, &mut scope)
+}
+
+// (begin body ...)
+fn begin<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    eval_body(args, env)
+}
+
+// (set! name expr)
+// Rebinds the nearest existing binding of `name` instead of shadowing it.
+fn set<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    let [target, expr] = exactly::<2>(args)?;
+    match &target.kind {
+        ExprKind::Ident(name) => {
+            let value = eval_expr(expr, env)?;
+            if env.set_binding(name.clone(), value) {
+                Ok(Step::Done(Value::None))
+            } else {
+                Err(Error::unbound(name.clone()).at(target.span))
+            }
+        }
+        _ => Err(Error::syntax("expected an identifier to set").at(target.span)),
+    }
+}
+
+// (lambda (param ...) body ...)
+// (lambda (param ... . rest) body ...)
+// (lambda rest body ...)
+// `rest` is bound to a list of the arguments left over after the named
+// parameters.
+fn lambda<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    let (params, body) = split_first(args)?;
+    Ok(Step::Done(Value::Lambda(Rc::new(make_lambda(params, body, env)?))))
+}
+
+// (defmacro name (param ...) body ...)
+// Defines a macro: when `(name arg ...)` is evaluated, the body is called with
+// the unevaluated args and the form it returns is evaluated in its place.
+fn defmacro<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    let (name, rest) = split_first(args)?;
+    let ExprKind::Ident(name) = &name.kind else {
+        return Err(Error::syntax("expected a macro name").at(name.span));
+    };
+    let (params, body) = split_first(rest)?;
+
+    let expander = make_lambda(params, body, env)?;
+    env.add_macro(name.clone(), Rc::new(expander));
+    Ok(Step::Done(Value::None))
+}
+
+// (%global name)
+// The top-level binding of `name`, even where a local binding shadows it. Macro
+// expansion produces it for identifiers that templates refer to.
+fn global<'a>(args: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    let [name] = exactly::<1>(args)?;
+    match &name.kind {
+        ExprKind::Ident(ident) => env
+            .get_global(ident)
+            .map(Step::Done)
+            .ok_or_else(|| Error::unbound(ident.clone()).at(name.span)),
+        _ => Err(Error::syntax("expected an identifier").at(name.span)),
+    }
+}
+
+// -------------------------------------------------------------------------- //
+// Helpers                                                                    //
+// -------------------------------------------------------------------------- //
+
+// Evaluates all but the last expression of `body`, which is in tail position.
+pub(super) fn eval_body<'a>(body: &'a [Expr], env: &mut Env) -> StepResult<'a> {
+    let Some((last, body)) = body.split_last() else {
+        return Ok(Step::Done(Value::None));
+    };
+    for expr in body {
+        eval_expr(expr, env)?;
+    }
+    Ok(Step::Eval(last, env.clone()))
+}
+
+// The body is copied once here, calls to the lambda evaluate it by reference.
+fn make_lambda(params: &Expr, body: &[Expr], env: &Env) -> Result<Lambda, Error> {
+    let to_name = |param: &Expr| match &param.kind {
+        ExprKind::Ident(name) => Ok(name.clone()),
+        _ => Err(Error::syntax("parameters must be identifiers").at(param.span)),
+    };
+
+    let (params, rest) = match &params.kind {
+        ExprKind::Ident(rest) => (Vec::new(), Some(rest.clone())),
+        ExprKind::List(params) => {
+            let mut names = params.iter().map(to_name).collect::<Result<Vec<_>, _>>()?;
+            match names.iter().position(|name| name == ".") {
+                Some(dot) if dot + 2 == names.len() => {
+                    let rest = names.pop();
+                    names.pop();
+                    (names, rest)
+                }
+                Some(_) => return Err(Error::syntax("expected a single parameter after `.`")),
+                None => (names, None),
+            }
+        }
+        _ => return Err(Error::syntax("expected a parameter list").at(params.span)),
+    };
+    if body.is_empty() {
+        return Err(Error::syntax("lambda body must not be empty"));
+    }
+
+    Ok(Lambda {
+        params,
+        rest,
+        body: body.to_vec(),
+        env: env.clone(),
+    })
+}
+
+fn exactly<const N: usize>(args: &[Expr]) -> Result<&[Expr; N], Error> {
+    args.try_into()
+        .map_err(|_| Error::arity(Arity::Exactly(N), args.len()))
+}
+
+fn split_first(args: &[Expr]) -> Result<(&Expr, &[Expr]), Error> {
+    args.split_first().ok_or(Error::arity(Arity::AtLeast(1), 0))
+}
+
+fn parse_bindings(bindings: &Expr) -> Result<Vec<(String, &Expr)>, Error> {
+    let ExprKind::List(items) = &bindings.kind else {
+        return Err(Error::syntax("expected a list of bindings").at(bindings.span));
+    };
+
+    let invalid = |span| Error::syntax("bindings must have the form (name init)").at(span);
+    items
+        .iter()
+        .map(|binding| match &binding.kind {
+            ExprKind::List(pair) => match <&[Expr; 2]>::try_from(pair.as_slice()) {
+                Ok([Expr { kind: ExprKind::Ident(name), .. }, init]) => Ok((name.clone(), init)),
+                _ => Err(invalid(binding.span)),
+            },
+            _ => Err(invalid(binding.span)),
+        })
+        .collect()
+}
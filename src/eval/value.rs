@@ -1,43 +1,452 @@
-use std::rc::Rc;
+use std::{
+    cell::{Cell, RefCell},
+    collections::hash_map::DefaultHasher,
+    hash::{BuildHasherDefault, Hash, Hasher},
+    rc::Rc,
+};
 
 use num_bigint::BigInt;
+use num_rational::BigRational;
 
-use super::env::Env;
+use crate::ast::Expr;
+
+use super::{env::Env, error::EResult, keyword::Keyword};
+
+pub type BuiltinFn = dyn Fn(Vec<Value>, &mut Env) -> EResult;
+
+// Persistent collections: updating one gives a new collection that shares most
+// of its structure with the old one, which is left untouched. They all use the
+// same deterministic hasher, so equal collections iterate (and print) in the
+// same order.
+pub type Map = im_rc::HashMap<Value, Value, BuildHasherDefault<DefaultHasher>>;
+pub type Set = im_rc::HashSet<Value, BuildHasherDefault<DefaultHasher>>;
 
 #[derive(Clone)]
 pub enum Value {
     Int(BigInt),
+    Float(f64),
+    // Never an integer, those are always `Int`s
+    Ratio(BigRational),
     Str(String),
+    Char(char),
     Bool(bool),
-    Fun(Rc<dyn Fn(Vec<Value>, &mut Env) -> Result<Value, ()>>),
+    Fun(Rc<BuiltinFn>),
+    Lambda(Rc<Lambda>),
+    Pair(Rc<Pair>),
+    // The only mutable value, through vector-set!
+    Vector(Rc<RefCell<Vec<Value>>>),
+    Map(Map),
+    Set(Set),
     Nil,
     Symbol(String),
+    Keyword(Keyword),
     None,
 }
 
+// A user-defined function. `env` is the environment the lambda was created in,
+// parameters are bound in a fresh frame on top of it on every call. If there is
+// a `rest` parameter, it gets the list of any remaining arguments.
+pub struct Lambda {
+    pub params: Vec<String>,
+    pub rest: Option<String>,
+    pub body: Vec<Expr>,
+    pub env: Env,
+}
+
+// A cons cell. Lists are chains of pairs linked through `cdr`, ending in `Nil`.
+pub struct Pair {
+    pub car: Value,
+    pub cdr: Value,
+}
+
+impl Value {
+    pub fn cons(car: Value, cdr: Value) -> Self {
+        Self::Pair(Rc::new(Pair { car, cdr }))
+    }
+
+    // Builds a proper list out of `items`.
+    pub fn list<I>(items: I) -> Self
+    where
+        I: IntoIterator<Item = Value>,
+        I::IntoIter: DoubleEndedIterator,
+    {
+        Self::list_with_tail(items, Self::Nil)
+    }
+
+    // Builds `(item ... . tail)`.
+    pub fn list_with_tail<I>(items: I, tail: Value) -> Self
+    where
+        I: IntoIterator<Item = Value>,
+        I::IntoIter: DoubleEndedIterator,
+    {
+        items
+            .into_iter()
+            .rev()
+            .fold(tail, |list, item| Self::cons(item, list))
+    }
+
+    pub fn vector(items: Vec<Value>) -> Self {
+        Self::Vector(Rc::new(RefCell::new(items)))
+    }
+
+    // Elements of a proper list, or `None` if `self` isn't one.
+    pub fn list_items(&self) -> Option<Vec<Value>> {
+        let mut items = Vec::new();
+        let mut current = self;
+        loop {
+            match current {
+                Self::Pair(pair) => {
+                    items.push(pair.car.clone());
+                    current = &pair.cdr;
+                }
+                Self::Nil => return Some(items),
+                _ => return None,
+            }
+        }
+    }
+
+    // An exact fraction, as an `Int` if it is a whole number.
+    pub fn ratio(value: BigRational) -> Self {
+        if value.is_integer() {
+            Self::Int(value.to_integer())
+        } else {
+            Self::Ratio(value)
+        }
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, Self::Int(_) | Self::Float(_) | Self::Ratio(_))
+    }
+
+    // The `eqv?` equivalence. Pairs, vectors, lambdas and builtins are the
+    // same only if they are the same object. Every other value is immutable
+    // and has no identity to speak of, so it is compared by kind and value:
+    // (eqv? 1 1.0) is false, and floats are compared bit for bit, so NaN is
+    // eqv to itself.
+    pub fn is_eqv(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Pair(lhs), Self::Pair(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::Vector(lhs), Self::Vector(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (lhs, rhs) => lhs == rhs,
+        }
+    }
+
+    // Everything except `false` counts as true in conditionals.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Self::Bool(false))
+    }
+
+    // Name of the value's type, as used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Int(_) => "int",
+            Self::Float(_) => "float",
+            Self::Ratio(_) => "ratio",
+            Self::Str(_) => "string",
+            Self::Char(_) => "char",
+            Self::Bool(_) => "bool",
+            Self::Fun(_) => "builtin",
+            Self::Lambda(_) => "lambda",
+            Self::Pair(_) => "pair",
+            Self::Vector(_) => "vector",
+            Self::Map(_) => "map",
+            Self::Set(_) => "set",
+            Self::Nil => "nil",
+            Self::Symbol(_) => "symbol",
+            Self::Keyword(_) => "keyword",
+            Self::None => "none",
+        }
+    }
+}
+
+// Structural equality, the `equal?` of the language: lists are equal if their
+// elements are. Values of different kinds are never equal, not even numbers:
+// numeric equality is the `=` builtin's business. Floats are compared bit for
+// bit, like `eqv?` does, so NaN is equal to itself and 0.0 isn't equal to -0.0.
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Int(lhs), Self::Int(rhs)) => lhs == rhs,
+            (Self::Float(lhs), Self::Float(rhs)) => lhs.to_bits() == rhs.to_bits(),
+            (Self::Ratio(lhs), Self::Ratio(rhs)) => lhs == rhs,
             (Self::Str(lhs), Self::Str(rhs)) => lhs == rhs,
+            (Self::Char(lhs), Self::Char(rhs)) => lhs == rhs,
             (Self::Bool(lhs), Self::Bool(rhs)) => lhs == rhs,
             (Self::Symbol(lhs), Self::Symbol(rhs)) => lhs == rhs,
-            (Self::Fun(_), Self::Fun(_)) => false,
-            _ => true
+            (Self::Keyword(lhs), Self::Keyword(rhs)) => lhs == rhs,
+            (Self::Fun(lhs), Self::Fun(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::Lambda(lhs), Self::Lambda(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::Pair(lhs), Self::Pair(rhs)) => {
+                // Walk the `cdr`s iteratively so that long lists don't
+                // overflow the stack
+                let (mut lhs, mut rhs) = (lhs, rhs);
+                loop {
+                    if lhs.car != rhs.car {
+                        return false;
+                    }
+                    match (&lhs.cdr, &rhs.cdr) {
+                        (Self::Pair(l), Self::Pair(r)) => (lhs, rhs) = (l, r),
+                        (l, r) => return l == r,
+                    }
+                }
+            }
+            (Self::Vector(lhs), Self::Vector(rhs)) => {
+                // Vectors can contain themselves. Two vectors that are already
+                // being compared further up are taken to be equal here: if
+                // they aren't, the comparison in progress finds out.
+                let key = (Rc::as_ptr(lhs), Rc::as_ptr(rhs));
+                if Rc::ptr_eq(lhs, rhs) || COMPARING.with_borrow(|pairs| pairs.contains(&key)) {
+                    return true;
+                }
+                COMPARING.with_borrow_mut(|pairs| pairs.push(key));
+                let equal = *lhs.borrow() == *rhs.borrow();
+                COMPARING.with_borrow_mut(|pairs| pairs.pop());
+                equal
+            }
+            (Self::Map(lhs), Self::Map(rhs)) => lhs == rhs,
+            (Self::Set(lhs), Self::Set(rhs)) => lhs == rhs,
+            (Self::Nil, Self::Nil) | (Self::None, Self::None) => true,
+            _ => false,
         }
     }
 }
 
+// Every value is equal to itself, NaN included, since floats are compared bit
+// for bit.
+impl Eq for Value {}
+
+type VectorPtr = *const RefCell<Vec<Value>>;
+
+thread_local! {
+    // The pairs of vectors `eq` is in the middle of comparing.
+    static COMPARING: RefCell<Vec<(VectorPtr, VectorPtr)>> = const { RefCell::new(Vec::new()) };
+    // Whether `hash` is in the middle of hashing a vector.
+    static HASHING_VECTOR: Cell<bool> = const { Cell::new(false) };
+}
+
+// Consistent with `PartialEq`: floats hash their bits, functions hash by
+// address, and maps and sets hash the same no matter the order of their
+// entries. Vectors hash their current contents: changing a vector that is used
+// as a key loses track of it. Vectors nested in a vector only hash their
+// length, since a vector can contain itself.
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::Int(value) => value.hash(state),
+            Self::Float(value) => value.to_bits().hash(state),
+            Self::Ratio(value) => value.hash(state),
+            Self::Str(value) => value.hash(state),
+            Self::Char(value) => value.hash(state),
+            Self::Bool(value) => value.hash(state),
+            Self::Symbol(name) => name.hash(state),
+            Self::Keyword(keyword) => keyword.hash(state),
+            Self::Fun(fun) => (Rc::as_ptr(fun) as *const ()).hash(state),
+            Self::Lambda(lambda) => Rc::as_ptr(lambda).hash(state),
+            Self::Pair(pair) => {
+                // Iterative for the same reason as `eq`
+                let mut current = pair;
+                loop {
+                    current.car.hash(state);
+                    match &current.cdr {
+                        Self::Pair(next) => current = next,
+                        tail => break tail.hash(state),
+                    }
+                }
+            }
+            Self::Vector(items) if HASHING_VECTOR.get() => items.borrow().len().hash(state),
+            Self::Vector(items) => {
+                HASHING_VECTOR.set(true);
+                items.borrow().hash(state);
+                HASHING_VECTOR.set(false);
+            }
+            Self::Map(map) => hash_unordered(map.iter(), state),
+            Self::Set(set) => hash_unordered(set.iter(), state),
+            Self::Nil | Self::None => {}
+        }
+    }
+}
+
+// Combines the hashes of `items` with a commutative operation.
+fn hash_unordered<T: Hash, H: Hasher>(items: impl Iterator<Item = T>, state: &mut H) {
+    let mut sum = 0u64;
+    for item in items {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        sum = sum.wrapping_add(hasher.finish());
+    }
+    sum.hash(state);
+}
+
+// The human form of values, what `display` prints: strings and characters
+// print as their contents.
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.print(f, false, &mut Vec::new())
+    }
+}
+
+// The readable form of a value, what `write` prints: reading it back gives an
+// equal value. Functions, NaN and infinities, symbols whose names aren't
+// identifiers and vectors that contain themselves have no readable form, they
+// print like `display` does.
+pub struct Written<'a>(&'a Value);
+
+impl std::fmt::Display for Written<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.print(f, true, &mut Vec::new())
+    }
+}
+
+impl Value {
+    pub fn written(&self) -> Written<'_> {
+        Written(self)
+    }
+
+    // `vectors` are the vectors being printed, a vector found inside itself
+    // prints as `#<cycle>`.
+    fn print(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        readable: bool,
+        vectors: &mut Vec<VectorPtr>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::Int(value) => write!(f, "{value}"),
+            // Debug formatting keeps the `.0` of whole floats
+            Self::Float(value) => write!(f, "{value:?}"),
+            Self::Ratio(value) => write!(f, "{value}"),
+            Self::Str(value) if readable => write_string(f, value),
+            Self::Str(value) => write!(f, "{value}"),
+            Self::Char(value) if readable => write_char(f, *value),
+            Self::Char(value) => write!(f, "{value}"),
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Fun(_) => write!(f, "#<builtin>"),
+            Self::Lambda(_) => write!(f, "#<lambda>"),
+            Self::Pair(pair) => {
+                write!(f, "(")?;
+                pair.car.print(f, readable, vectors)?;
+                let mut rest = &pair.cdr;
+                while let Self::Pair(pair) = rest {
+                    write!(f, " ")?;
+                    pair.car.print(f, readable, vectors)?;
+                    rest = &pair.cdr;
+                }
+                if !matches!(rest, Self::Nil) {
+                    write!(f, " . ")?;
+                    rest.print(f, readable, vectors)?;
+                }
+                write!(f, ")")
+            }
+            Self::Vector(items) if vectors.contains(&Rc::as_ptr(items)) => write!(f, "#<cycle>"),
+            Self::Vector(items) => {
+                write!(f, "[")?;
+                vectors.push(Rc::as_ptr(items));
+                print_items(f, items.borrow().iter(), readable, vectors)?;
+                vectors.pop();
+                write!(f, "]")
+            }
+            Self::Map(map) => {
+                write!(f, "{{")?;
+                let entries = map.iter().flat_map(|(key, value)| [key, value]);
+                print_items(f, entries, readable, vectors)?;
+                write!(f, "}}")
+            }
+            Self::Set(set) => {
+                write!(f, "#{{")?;
+                print_items(f, set.iter(), readable, vectors)?;
+                write!(f, "}}")
+            }
+            Self::Nil => write!(f, "()"),
+            Self::Symbol(name) => write!(f, "{name}"),
+            Self::Keyword(keyword) => write!(f, "{keyword}"),
+            Self::None => write!(f, "#<none>"),
+        }
+    }
+}
+
+// `items`, separated by spaces.
+fn print_items<'a>(
+    f: &mut std::fmt::Formatter<'_>,
+    items: impl Iterator<Item = &'a Value>,
+    readable: bool,
+    vectors: &mut Vec<VectorPtr>,
+) -> std::fmt::Result {
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        item.print(f, readable, vectors)?;
+    }
+    Ok(())
+}
+
+// Escapes what the lexer would otherwise read differently, and control
+// characters so that they stay visible.
+fn write_string(f: &mut std::fmt::Formatter<'_>, string: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in string.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            '\r' => write!(f, "\\r")?,
+            '\0' => write!(f, "\\0")?,
+            c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+// Invisible characters get a name, or their code in hex.
+fn write_char(f: &mut std::fmt::Formatter<'_>, c: char) -> std::fmt::Result {
+    match c {
+        ' ' => write!(f, "#\\space"),
+        '\n' => write!(f, "#\\newline"),
+        '\t' => write!(f, "#\\tab"),
+        '\r' => write!(f, "#\\return"),
+        '\0' => write!(f, "#\\nul"),
+        '\u{1b}' => write!(f, "#\\escape"),
+        '\u{7f}' => write!(f, "#\\delete"),
+        c if c.is_whitespace() || c.is_control() => write!(f, "#\\x{:x}", c as u32),
+        c => write!(f, "#\\{c}"),
+    }
+}
+
 impl std::fmt::Debug for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Int(arg0) => f.debug_tuple("Int").field(arg0).finish(),
+            Self::Float(arg0) => f.debug_tuple("Float").field(arg0).finish(),
+            Self::Ratio(arg0) => f.debug_tuple("Ratio").field(arg0).finish(),
             Self::Str(arg0) => f.debug_tuple("Str").field(arg0).finish(),
+            Self::Char(arg0) => f.debug_tuple("Char").field(arg0).finish(),
             Self::Bool(arg0) => f.debug_tuple("Bool").field(arg0).finish(),
             Self::Fun(_) => write!(f, "Fun"),
+            Self::Lambda(_) => write!(f, "Lambda"),
+            Self::Pair(pair) => f.debug_tuple("Pair").field(&pair.car).field(&pair.cdr).finish(),
+            Self::Vector(items) => f.debug_tuple("Vector").field(&items.borrow()).finish(),
+            Self::Map(map) => f.debug_tuple("Map").field(map).finish(),
+            Self::Set(set) => f.debug_tuple("Set").field(set).finish(),
             Self::Nil => write!(f, "Nil"),
             Self::Symbol(arg0) => f.debug_tuple("Symbol").field(arg0).finish(),
+            Self::Keyword(arg0) => f.debug_tuple("Keyword").field(arg0).finish(),
             Self::None => write!(f, "None"),
         }
     }
+}
+
+// Dropping a long list would otherwise recurse once per element
+impl Drop for Pair {
+    fn drop(&mut self) {
+        let mut next = std::mem::replace(&mut self.cdr, Value::Nil);
+        while let Value::Pair(pair) = next {
+            match Rc::try_unwrap(pair) {
+                Ok(mut pair) => next = std::mem::replace(&mut pair.cdr, Value::Nil),
+                Err(_) => break,
+            }
+        }
+    }
 }
\ No newline at end of file
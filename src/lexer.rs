@@ -1,27 +1,56 @@
-use itertools::Itertools;
-use std::iter::Peekable;
+use std::fmt;
 
-use crate::token::Token;
+use itertools::{peek_nth, PeekNth};
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+use crate::span::{Span, Spanned};
+use crate::token::{Token, TokenKind};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Error {
-    UnexpectedSymbol(char),
-    UnclosedString,
+    UnexpectedSymbol(char, Span),
+    UnclosedString(Span),
+    InvalidNumber(Span),
+    UnclosedComment(Span),
+    InvalidEscape(Span),
+    InvalidChar(Span),
 }
 
 type TokResult = Result<Token, Error>;
+type KindResult = Result<TokenKind, Error>;
 
 // -------------------------------------------------------------------------- //
 // Main struct                                                                //
 // -------------------------------------------------------------------------- //
 
 pub struct Lexer<I: Iterator<Item = char>> {
-    source: Peekable<I>,
+    source: PeekNth<I>,
+    // Position of the next char in the source
+    offset: usize,
+    line: usize,
+    column: usize,
+    // Whether comments are returned as tokens rather than skipped
+    keep_comments: bool,
 }
 
 impl<I: Iterator<Item = char>> Lexer<I> {
-    fn new(source: Peekable<I>) -> Self {
-        Self { source }
+    fn new(source: I) -> Self {
+        let mut lexer = Self {
+            source: peek_nth(source),
+            offset: 0,
+            line: 1,
+            column: 1,
+            keep_comments: false,
+        };
+        lexer.skip_shebang();
+        lexer
+    }
+
+    // Makes the lexer return `;` and `#| |#` comments as `Comment` tokens, for
+    // tools that need to preserve them. The parser ignores them.
+    pub fn with_comments(mut self) -> Self {
+        self.keep_comments = true;
+        self
     }
 
     // ---------------------------------------------------------------------- //
@@ -29,77 +58,319 @@ impl<I: Iterator<Item = char>> Lexer<I> {
     // ---------------------------------------------------------------------- //
 
     fn next_token(&mut self) -> Option<TokResult> {
-        use Token::*;
+        loop {
+            match self.lex_token()? {
+                Ok(Token { kind: TokenKind::Comment(_), .. }) if !self.keep_comments => continue,
+                token => return Some(token),
+            }
+        }
+    }
+
+    fn lex_token(&mut self) -> Option<TokResult> {
+        use TokenKind::*;
 
         self.skip_whitespace();
-        let next_char = self.peek()?;
+        let next_char = *self.peek()?;
+        let start = self.current_position();
 
         let next_token = match next_char {
             '(' => self.accept(LParen),
             ')' => self.accept(RParen),
+            '[' => self.accept(LBracket),
+            ']' => self.accept(RBracket),
+            '{' => self.accept(LBrace),
+            '}' => self.accept(RBrace),
+            '#' if self.source.peek_nth(1) == Some(&'{') => {
+                self.consume();
+                self.accept(HashLBrace)
+            }
             '\'' => self.accept(Quote),
-            c if Self::starts_identifier(c) => self.parse_identifier(),
-            c if Self::starts_integer(c) => self.parse_integer(),
-            c if Self::starts_string(c) => self.parse_string(),
+            '`' => self.accept(Quasiquote),
+            ',' => self.parse_unquote(),
+            ';' => self.parse_line_comment(),
+            '#' if self.source.peek_nth(1) == Some(&'|') => self.parse_block_comment(start),
+            '#' if self.starts_raw_string() => self.parse_raw_string(start),
+            '#' if self.source.peek_nth(1) == Some(&'\\') => self.parse_char(start),
+            '#' if self.source.peek_nth(1) == Some(&';') => {
+                self.consume();
+                self.accept(DatumComment)
+            }
+            _ if self.starts_number() => self.parse_number(start),
+            ':' if self.peek_nth_is(1, Self::continues_identifier) => self.parse_keyword(),
+            c if Self::starts_identifier(&c) => self.parse_identifier(),
+            c if Self::starts_string(&c) => self.parse_string(start),
             // Unexpected symbol
-            c => Err(Error::UnexpectedSymbol(c.clone())),
+            c => {
+                self.consume();
+                Err(Error::UnexpectedSymbol(c, self.span_from(start)))
+            }
         };
 
-        Some(next_token)
+        Some(next_token.map(|kind| Token {
+            kind,
+            span: self.span_from(start),
+        }))
     }
 
     // ---------------------------------------------------------------------- //
     // Whitespace handling                                                    //
     // ---------------------------------------------------------------------- //
 
+    // Unicode whitespace, including non-breaking and other wide spaces
     fn is_whitespace(c: &char) -> bool {
-        c.is_ascii_whitespace()
+        c.is_whitespace()
     }
     fn skip_whitespace(&mut self) {
         self.skip_while(Self::is_whitespace);
     }
+    // A `#!` line at the very start of the source, so that scripts can be
+    // made executable.
+    fn skip_shebang(&mut self) {
+        if self.source.peek_nth(0) == Some(&'#') && self.source.peek_nth(1) == Some(&'!') {
+            self.skip_while(|&c| c != '\n');
+        }
+    }
 
     // ---------------------------------------------------------------------- //
     // Token specific lexing methods                                          //
     // ---------------------------------------------------------------------- //
 
     // Identifier ----------------------------------------------------------- //
+    // Identifiers follow UAX #31 (they start with an XID_Start character and
+    // continue with XID_Continue ones), except that operator characters are
+    // allowed anywhere: ASCII punctuation other than the delimiters, and any
+    // non-ASCII character that is neither part of a word, whitespace nor a
+    // control character, e.g. `→` or `≤`.
     fn starts_identifier(c: &char) -> bool {
-        c.is_ascii_graphic() && !c.is_ascii_digit() && !"()'\"".contains(*c)
+        !"()[]{}'`,\";".contains(*c) && (is_xid_start(*c) || Self::is_operator(c))
     }
-    fn parse_identifier(&mut self) -> TokResult {
-        let matcher = |c: &char| c.is_ascii_graphic() && !"()\"".contains(*c);
-        let string = self.collect_while(matcher);
+    fn continues_identifier(c: &char) -> bool {
+        !"()[]{}`,\";".contains(*c) && (is_xid_continue(*c) || Self::is_operator(c))
+    }
+    fn is_operator(c: &char) -> bool {
+        if c.is_ascii() {
+            c.is_ascii_punctuation()
+        } else {
+            !is_xid_continue(*c) && !c.is_whitespace() && !c.is_control()
+        }
+    }
+    fn parse_identifier(&mut self) -> KindResult {
+        let string = self.collect_while(Self::continues_identifier);
 
         Ok(match &string[..] {
-            "true"  => Token::Boolean(true),
-            "false" => Token::Boolean(false),
-            _ => Token::Identifier(string),
+            "true"  => TokenKind::Boolean(true),
+            "false" => TokenKind::Boolean(false),
+            _ => TokenKind::Identifier(string),
         })
     }
 
-    // Integer -------------------------------------------------------------- //
-    fn starts_integer(c: &char) -> bool {
-        c.is_ascii_digit()
+    // Keyword -------------------------------------------------------------- //
+    // A colon followed by the characters of an identifier. A lone colon is an
+    // identifier itself.
+    fn parse_keyword(&mut self) -> KindResult {
+        self.consume();
+        Ok(TokenKind::Keyword(self.collect_while(Self::continues_identifier)))
     }
-    fn parse_integer(&mut self) -> TokResult {
-        Ok(Token::Integer(self.collect_while(char::is_ascii_digit)))
+
+    // Unquote -------------------------------------------------------------- //
+    fn parse_unquote(&mut self) -> KindResult {
+        self.consume();
+        if self.peek() == Some(&'@') {
+            self.accept(TokenKind::UnquoteSplicing)
+        } else {
+            Ok(TokenKind::Unquote)
+        }
+    }
+
+    // Comments ------------------------------------------------------------- //
+    fn parse_line_comment(&mut self) -> KindResult {
+        Ok(TokenKind::Comment(self.collect_while(|&c| c != '\n')))
+    }
+    // #| ... |#, which may contain other block comments
+    fn parse_block_comment(&mut self, start: Position) -> KindResult {
+        let mut comment = String::new();
+        let mut depth = 0;
+        loop {
+            match (self.source.peek_nth(0).copied(), self.source.peek_nth(1).copied()) {
+                (Some('#'), Some('|')) => depth += 1,
+                (Some('|'), Some('#')) => depth -= 1,
+                (Some(_), _) => {
+                    comment.extend(self.consume());
+                    continue;
+                }
+                (None, _) => return Err(Error::UnclosedComment(self.span_from(start))),
+            }
+            comment.extend(self.consume());
+            comment.extend(self.consume());
+            if depth == 0 {
+                return Ok(TokenKind::Comment(comment));
+            }
+        }
+    }
+
+    // Character ------------------------------------------------------------ //
+    // #\a, #\( or #\λ for the character itself, #\space or #\newline by
+    // name, #\x41 by its hexadecimal code.
+    fn parse_char(&mut self, start: Position) -> KindResult {
+        self.consume();
+        self.consume();
+        let Some(first) = self.consume() else {
+            return Err(Error::InvalidChar(self.span_from(start)));
+        };
+        if !first.is_alphanumeric() || !self.peek().is_some_and(Self::continues_identifier) {
+            return Ok(TokenKind::Char(first));
+        }
+
+        let name = format!("{first}{}", self.collect_while(Self::continues_identifier));
+        let c = match &name[..] {
+            "space" => Some(' '),
+            "newline" => Some('\n'),
+            "tab" => Some('\t'),
+            "return" => Some('\r'),
+            "nul" | "null" => Some('\0'),
+            "escape" => Some('\u{1b}'),
+            "delete" => Some('\u{7f}'),
+            _ => name
+                .strip_prefix('x')
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .and_then(char::from_u32),
+        };
+        c.map(TokenKind::Char)
+            .ok_or(Error::InvalidChar(self.span_from(start)))
+    }
+
+    // Number --------------------------------------------------------------- //
+    // A sign only starts a number if a digit follows, `-` and `-x` are
+    // identifiers.
+    fn starts_number(&mut self) -> bool {
+        match self.peek() {
+            Some('+' | '-') => self.peek_nth_is(1, char::is_ascii_digit),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        }
+    }
+    // Integers (-12), ratios (1/3) and floats (1.5, 2e10, -1.5e-3).
+    fn parse_number(&mut self, start: Position) -> KindResult {
+        let mut string = String::new();
+        if matches!(self.peek(), Some('+' | '-')) {
+            string.extend(self.consume());
+        }
+        string += &self.collect_while(char::is_ascii_digit);
+
+        if self.peek() == Some(&'/') && self.peek_nth_is(1, char::is_ascii_digit) {
+            string.extend(self.consume());
+            let denominator = self.collect_while(char::is_ascii_digit);
+            if denominator.chars().all(|c| c == '0') {
+                return Err(Error::InvalidNumber(self.span_from(start)));
+            }
+            string += &denominator;
+            return Ok(TokenKind::Ratio(string));
+        }
+
+        let mut float = false;
+        if self.peek() == Some(&'.') && self.peek_nth_is(1, char::is_ascii_digit) {
+            string.extend(self.consume());
+            string += &self.collect_while(char::is_ascii_digit);
+            float = true;
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let sign = matches!(self.source.peek_nth(1), Some('+' | '-')) as usize;
+            if self.peek_nth_is(1 + sign, char::is_ascii_digit) {
+                for _ in 0..=sign {
+                    string.extend(self.consume());
+                }
+                string += &self.collect_while(char::is_ascii_digit);
+                float = true;
+            }
+        }
+
+        Ok(if float { TokenKind::Float(string) } else { TokenKind::Integer(string) })
     }
 
     // String --------------------------------------------------------------- //
     fn starts_string(c: &char) -> bool {
         *c == '"'
     }
-    fn parse_string(&mut self) -> TokResult {
+    fn parse_string(&mut self, start: Position) -> KindResult {
         self.consume();
-        let contents = self.collect_while(|&c| c != '"');
+        let mut contents = String::new();
+        loop {
+            let escape = self.current_position();
+            match self.consume() {
+                Some('"') => return Ok(TokenKind::String(contents)),
+                Some('\\') => contents.extend(self.parse_escape(start, escape)?),
+                Some(c) => contents.push(c),
+                // Missing the closing " on a string. Reached EOF
+                None => return Err(Error::UnclosedString(self.span_from(start))),
+            }
+        }
+    }
+    // The part of an escape sequence after the \. A \ at the end of a line
+    // skips the line break and the indentation of the next line.
+    fn parse_escape(&mut self, start: Position, escape: Position) -> Result<Option<char>, Error> {
+        let c = match self.consume() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('\n') => {
+                self.skip_whitespace();
+                return Ok(None);
+            }
+            // \u{1F600}
+            Some('u') if self.peek() == Some(&'{') => {
+                self.consume();
+                let digits = self.collect_while(char::is_ascii_hexdigit);
+                // Without the }, whatever comes next belongs to the string
+                let closed = self.peek() == Some(&'}');
+                if closed {
+                    self.consume();
+                }
+                let c = u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32);
+                match c {
+                    Some(c) if closed && digits.len() <= 6 => c,
+                    _ if self.peek().is_none() => {
+                        return Err(Error::UnclosedString(self.span_from(start)))
+                    }
+                    _ => return Err(Error::InvalidEscape(self.span_from(escape))),
+                }
+            }
+            Some(_) => return Err(Error::InvalidEscape(self.span_from(escape))),
+            None => return Err(Error::UnclosedString(self.span_from(start))),
+        };
+        Ok(Some(c))
+    }
 
-        if self.peek().is_none() {
-            // Missing the closing " on a string. Reached EOF
-            Err(Error::UnclosedString)
-        } else {
-            self.consume();
-            Ok(Token::String(contents))
+    // #r"...", no escapes. Any number of # can follow the r, the string then
+    // ends at a " followed by as many #: #r#"a "quoted" word"#
+    fn starts_raw_string(&mut self) -> bool {
+        self.source.peek_nth(1) == Some(&'r')
+            && matches!(self.source.peek_nth(2), Some('"' | '#'))
+    }
+    fn parse_raw_string(&mut self, start: Position) -> KindResult {
+        self.consume();
+        self.consume();
+        let hashes = self.collect_while(|&c| c == '#').len();
+        match self.consume() {
+            Some('"') => {}
+            Some(c) => return Err(Error::UnexpectedSymbol(c, self.span_from(start))),
+            None => return Err(Error::UnclosedString(self.span_from(start))),
+        }
+
+        let mut contents = String::new();
+        loop {
+            match self.consume() {
+                Some('"') if (0..hashes).all(|n| self.source.peek_nth(n) == Some(&'#')) => {
+                    for _ in 0..hashes {
+                        self.consume();
+                    }
+                    return Ok(TokenKind::String(contents));
+                }
+                Some(c) => contents.push(c),
+                None => return Err(Error::UnclosedString(self.span_from(start))),
+            }
         }
     }
 
@@ -111,27 +382,99 @@ impl<I: Iterator<Item = char>> Lexer<I> {
     fn peek(&mut self) -> Option<&char> {
         self.source.peek()
     }
-    fn consume(&mut self) {
-        self.source.next();
+    fn consume(&mut self) -> Option<char> {
+        let c = self.source.next()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
     }
-    fn accept(&mut self, t: Token) -> TokResult {
+    fn peek_nth_is(&mut self, n: usize, matcher: impl FnOnce(&char) -> bool) -> bool {
+        self.source.peek_nth(n).is_some_and(matcher)
+    }
+    fn accept(&mut self, t: TokenKind) -> KindResult {
         self.consume();
         Ok(t)
     }
 
     // Multi element -------------------------------------------------------- //
-    fn collect_while<F: FnMut(&char) -> bool>(&mut self, matcher: F) -> String {
-        self.source.peeking_take_while(matcher).collect()
+    fn collect_while<F: FnMut(&char) -> bool>(&mut self, mut matcher: F) -> String {
+        let mut string = String::new();
+        while self.peek().is_some_and(&mut matcher) {
+            string.extend(self.consume());
+        }
+        string
     }
-    fn skip_while<F: FnMut(&char) -> bool>(&mut self, matcher: F) {
-        self.source.peeking_take_while(matcher).for_each(drop)
+    fn skip_while<F: FnMut(&char) -> bool>(&mut self, mut matcher: F) {
+        while self.peek().is_some_and(&mut matcher) {
+            self.consume();
+        }
+    }
+
+    // Positions ------------------------------------------------------------ //
+    fn current_position(&self) -> Position {
+        Position {
+            offset: self.offset,
+            line: self.line,
+            column: self.column,
+        }
+    }
+    fn span_from(&self, start: Position) -> Span {
+        Span {
+            start: start.offset,
+            end: self.offset,
+            line: start.line,
+            column: start.column,
+        }
     }
 }
 
+#[derive(Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
 // -------------------------------------------------------------------------- //
 // Trait implementations                                                      //
 // -------------------------------------------------------------------------- //
 
+// Display ------------------------------------------------------------------ //
+impl Spanned for Error {
+    fn span(&self) -> Option<Span> {
+        match *self {
+            Self::UnexpectedSymbol(_, span)
+            | Self::UnclosedString(span)
+            | Self::InvalidNumber(span)
+            | Self::UnclosedComment(span)
+            | Self::InvalidEscape(span)
+            | Self::InvalidChar(span) => Some(span),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedSymbol(c, span) => {
+                write!(f, "unexpected symbol {c:?} at {span}")
+            }
+            Self::UnclosedString(span) => write!(f, "unclosed string starting at {span}"),
+            Self::InvalidNumber(span) => write!(f, "invalid number at {span}"),
+            Self::UnclosedComment(span) => write!(f, "unclosed comment starting at {span}"),
+            Self::InvalidEscape(span) => write!(f, "invalid escape sequence at {span}"),
+            Self::InvalidChar(span) => write!(f, "invalid character literal at {span}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 // Iterator ----------------------------------------------------------------- //
 impl<I> Iterator for Lexer<I>
 where
@@ -150,14 +493,14 @@ where
     I: Iterator<Item = char>,
 {
     fn from(source: I) -> Self {
-        Self::new(source.peekable())
+        Self::new(source)
     }
 }
 
 // From for string slices --------------------------------------------------- //
 impl<'a> From<&'a str> for Lexer<std::str::Chars<'a>> {
     fn from(source: &'a str) -> Self {
-        Self::new(source.chars().peekable())
+        Self::new(source.chars())
     }
 }
 
@@ -170,13 +513,65 @@ mod tests {
     use super::*;
     use crate::token::test_macros::*;
 
+    fn span(start: usize, end: usize, line: usize, column: usize) -> Span {
+        Span { start, end, line, column }
+    }
+
+    #[test]
+    fn test_spans() {
+        let spans: Vec<_> = Lexer::from("(a\n  \"λ\" 12)")
+            .map(|token| token.unwrap().span)
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                span(0, 1, 1, 1),
+                span(1, 2, 1, 2),
+                span(5, 9, 2, 3),
+                span(10, 12, 2, 7),
+                span(12, 13, 2, 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_position() {
+        let err = Lexer::from("(a\n  \0)").find_map(Result::err).unwrap();
+        assert_eq!(err, Error::UnexpectedSymbol('\0', span(5, 6, 2, 3)));
+        assert_eq!(
+            err.render("(a\n  \0)"),
+            "unexpected symbol '\\0' at 2:3\n --> 2:3\n  |\n2 |   \0)\n  |   ^"
+        );
+    }
+
+    #[test]
+    fn test_comments_as_tokens() {
+        let tokens: Vec<_> = Lexer::from("a ; one\n#| two #| three |# |#b #;c")
+            .with_comments()
+            .map(|token| token.unwrap().kind)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                ident!("a"),
+                comment!("; one"),
+                comment!("#| two #| three |# |#"),
+                ident!("b"),
+                dc!(),
+                ident!("c"),
+            ]
+        );
+    }
+
     macro_rules! lexer_tests {
         ($($name:ident {$input:expr, $output:expr}),+ $(,)?) => {
             $(
                 #[test]
                 fn $name() {
                     assert_eq!(
-                        Lexer::from($input).collect::<Result<Vec<_>, _>>(),
+                        Lexer::from($input)
+                            .map(|token| token.map(|token| token.kind))
+                            .collect::<Result<Vec<_>, _>>(),
                         $output
                     );
                 }
@@ -189,10 +584,28 @@ mod tests {
             lp!(),
             rp!()
         ])},
-        test_err {"\0", Err(Error::UnexpectedSymbol('\0'))},
+        test_err {"\0", Err(Error::UnexpectedSymbol('\0', span(0, 1, 1, 1)))},
         test_identifier {"a124<./S?>F", Ok(vec![
             ident!("a124<./S?>F")
         ])},
+        test_unicode_identifiers {"(λ café → ≤x a·b _ŝ ü\u{308} 😀!)", Ok(vec![
+            lp!(),
+            ident!("λ"),
+            ident!("café"),
+            ident!("→"),
+            ident!("≤x"),
+            ident!("a·b"),
+            ident!("_ŝ"),
+            ident!("ü\u{308}"),
+            ident!("😀!"),
+            rp!()
+        ])},
+        test_unicode_whitespace {"a\u{a0}b\u{2003}\u{3000}c", Ok(vec![
+            ident!("a"),
+            ident!("b"),
+            ident!("c")
+        ])},
+        test_combining_mark_cannot_start {"\u{308}", Err(Error::UnexpectedSymbol('\u{308}', span(0, 2, 1, 1)))},
         test_integer_ok_single_digit {"1", Ok(vec![
             int!("1")
         ])},
@@ -205,7 +618,43 @@ mod tests {
         test_string_ok_multi {r#""12345""#, Ok(vec![
             str!("12345")
         ])},
-        test_string_err {r#""123"#, Err(Error::UnclosedString)},
+        test_string_err {r#""123"#, Err(Error::UnclosedString(span(0, 4, 1, 1)))},
+        test_string_escapes {r#""a\"b\\c\n\t\r\0\u{3bb}\u{1F600}""#, Ok(vec![
+            str!("a\"b\\c\n\t\r\0λ😀")
+        ])},
+        test_string_multi_line {"\"a\nb \\\n    c\"", Ok(vec![
+            str!("a\nb c")
+        ])},
+        test_string_invalid_escape {r#"(a "b\q")"#, Err(Error::InvalidEscape(span(5, 7, 1, 6)))},
+        test_string_invalid_unicode_escape {r#""\u{d800}""#, Err(Error::InvalidEscape(span(1, 9, 1, 2)))},
+        test_string_unclosed_escape {r#""ab\"#, Err(Error::UnclosedString(span(0, 4, 1, 1)))},
+        test_string_unbraced_unicode_escape {r#""\u{41""#, Err(Error::InvalidEscape(span(1, 6, 1, 2)))},
+        test_string_unclosed_unicode_escape {r#""\u{41"#, Err(Error::UnclosedString(span(0, 6, 1, 1)))},
+        test_raw_string {r###"#r"a\n" #r#"say "hi""# #r##"a"#b"##"###, Ok(vec![
+            str!("a\\n"),
+            str!("say \"hi\""),
+            str!("a\"#b")
+        ])},
+        test_raw_string_unclosed {r##"#r#"a""##, Err(Error::UnclosedString(span(0, 6, 1, 1)))},
+        test_chars {r"#\a #\A #\( #\  #\λ #\space #\newline #\x41 #\x3bb #\x", Ok(vec![
+            chr!('a'),
+            chr!('A'),
+            chr!('('),
+            chr!(' '),
+            chr!('λ'),
+            chr!(' '),
+            chr!('\n'),
+            chr!('A'),
+            chr!('λ'),
+            chr!('x')
+        ])},
+        test_char_in_list {r"(#\a)", Ok(vec![
+            lp!(),
+            chr!('a'),
+            rp!()
+        ])},
+        test_char_unknown_name {r"#\spaces", Err(Error::InvalidChar(span(0, 8, 1, 1)))},
+        test_char_eof {r"#\", Err(Error::InvalidChar(span(0, 2, 1, 1)))},
         test_bool_true {"true", Ok(vec![bool!(true)])},
         test_bool_false {"false", Ok(vec![bool!(false)])},
         test_bool_postfix {"atrue", Ok(vec![ident!("atrue")])},
@@ -218,10 +667,76 @@ mod tests {
         test_mixed_2 {"(+ -5-124<./S?>F 35)", Ok(vec![
             lp!(),
             ident!("+"),
-            ident!("-5-124<./S?>F"),
+            int!("-5"),
+            int!("-124"),
+            ident!("<./S?>F"),
             int!("35"),
             rp!()
         ])},
+        test_signs {"(- -x +1 -2)", Ok(vec![
+            lp!(),
+            ident!("-"),
+            ident!("-x"),
+            int!("+1"),
+            int!("-2"),
+            rp!()
+        ])},
+        test_floats {"1.5 -0.25 2e10 1.5E-3 1. 1e", Ok(vec![
+            float!("1.5"),
+            float!("-0.25"),
+            float!("2e10"),
+            float!("1.5E-3"),
+            int!("1"),
+            ident!("."),
+            int!("1"),
+            ident!("e")
+        ])},
+        test_ratios {"1/3 -10/4 1/", Ok(vec![
+            ratio!("1/3"),
+            ratio!("-10/4"),
+            int!("1"),
+            ident!("/")
+        ])},
+        test_line_comment {"(a ; b c)\n; d\ne;f", Ok(vec![
+            lp!(),
+            ident!("a"),
+            ident!("e")
+        ])},
+        test_block_comment {"a #| b #| c |# ) |# d", Ok(vec![
+            ident!("a"),
+            ident!("d")
+        ])},
+        test_unclosed_block_comment {"a #| b #| c |#", Err(Error::UnclosedComment(span(2, 14, 1, 3)))},
+        test_datum_comment {"#;(a) b", Ok(vec![
+            dc!(),
+            lp!(),
+            ident!("a"),
+            rp!(),
+            ident!("b")
+        ])},
+        test_zero_denominator {"1/00", Err(Error::InvalidNumber(span(0, 4, 1, 1)))},
+        test_shebang {"#!/usr/bin/env lisp-rs\n(a)", Ok(vec![
+            lp!(),
+            ident!("a"),
+            rp!()
+        ])},
+        test_shebang_only_at_start {"a #!b", Ok(vec![
+            ident!("a"),
+            ident!("#!b")
+        ])},
+        test_quasiquote {"`(a ,b ,@c d,e)", Ok(vec![
+            qq!(),
+            lp!(),
+            ident!("a"),
+            uq!(),
+            ident!("b"),
+            uqs!(),
+            ident!("c"),
+            ident!("d"),
+            uq!(),
+            ident!("e"),
+            rp!()
+        ])},
         test_quote_1 {"'1", Ok(vec![
             q!(),
             int!("1")
@@ -247,5 +762,39 @@ mod tests {
             rp!(),
             rp!()
         ])},
+        test_brackets {"[a[1]](]", Ok(vec![
+            lbk!(),
+            ident!("a"),
+            lbk!(),
+            int!("1"),
+            rbk!(),
+            rbk!(),
+            lp!(),
+            rbk!()
+        ])},
+        test_keywords {"(:a :b-c? : :: :1 a:b)", Ok(vec![
+            lp!(),
+            kw!("a"),
+            kw!("b-c?"),
+            ident!(":"),
+            kw!(":"),
+            kw!("1"),
+            ident!("a:b"),
+            rp!()
+        ])},
+        test_braces {"{a 1 b{}}#{1 #{}}", Ok(vec![
+            lb!(),
+            ident!("a"),
+            int!("1"),
+            ident!("b"),
+            lb!(),
+            rb!(),
+            rb!(),
+            hlb!(),
+            int!("1"),
+            hlb!(),
+            rb!(),
+            rb!()
+        ])},
     }
 }
@@ -1,3 +1,4 @@
+pub mod span;
 pub mod token;
 pub mod lexer;
 pub mod ast;
@@ -0,0 +1,137 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use lisp_rs::eval::{env::Env, evaluator::eval_toplevel, value::Value};
+use lisp_rs::{lexer::Lexer, parser::Parser, span::Spanned};
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+const PROMPT: &str = "λ> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+const HISTORY_FILE: &str = ".lisp-rs-history";
+
+// Without arguments, starts a REPL. Otherwise runs the script at the given
+// path, passing the remaining arguments on to it.
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.is_empty() {
+        match repl() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("{err}");
+                ExitCode::FAILURE
+            }
+        }
+    } else {
+        run_script(args)
+    }
+}
+
+// -------------------------------------------------------------------------- //
+// Scripts                                                                    //
+// -------------------------------------------------------------------------- //
+
+fn run_script(args: Vec<String>) -> ExitCode {
+    let path = args[0].clone();
+    let source = match std::fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let ast = match Parser::new(Lexer::from(&source[..])).parse() {
+        Ok(ast) => ast,
+        Err(err) => {
+            eprintln!("{path}: {}", err.render(&source));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match eval_toplevel(ast, &mut Env::with_args(args)) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{path}: {}", err.render(&source));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// -------------------------------------------------------------------------- //
+// REPL                                                                       //
+// -------------------------------------------------------------------------- //
+
+fn repl() -> rustyline::Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    let history = history_path();
+    if let Some(path) = &history {
+        // There is no history on the first run
+        let _ = editor.load_history(path);
+    }
+
+    let mut env = Env::default();
+    let mut input = String::new();
+
+    loop {
+        let prompt = if input.is_empty() { PROMPT } else { CONTINUATION_PROMPT };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                if !input.is_empty() {
+                    input.push('\n');
+                }
+                input.push_str(&line);
+
+                if input.trim().is_empty() {
+                    input.clear();
+                    continue;
+                }
+                if !read_eval_print(&input, &mut env) {
+                    // Keep reading until the expression is complete
+                    continue;
+                }
+
+                // Losing a history entry is no reason to end the session
+                if let Err(err) = editor.add_history_entry(input.as_str()) {
+                    eprintln!("warning: couldn't add to history: {err}");
+                }
+                input.clear();
+            }
+            // Ctrl-C discards the current input
+            Err(ReadlineError::Interrupted) => input.clear(),
+            // Ctrl-D exits
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err),
+        }
+    }
+
+    if let Some(path) = &history {
+        if let Err(err) = editor.save_history(path) {
+            eprintln!("warning: couldn't save history to {}: {err}", path.display());
+        }
+    }
+    Ok(())
+}
+
+// Evaluates `input` and prints the result in its readable form. Returns
+// `false` if `input` is an incomplete expression and nothing was evaluated.
+fn read_eval_print(input: &str, env: &mut Env) -> bool {
+    let ast = match Parser::new(Lexer::from(input)).parse() {
+        Ok(ast) => ast,
+        Err(err) if err.is_incomplete(input) => return false,
+        Err(err) => {
+            eprintln!("{}", err.render(input));
+            return true;
+        }
+    };
+
+    match eval_toplevel(ast, env) {
+        Ok(Value::None) => {}
+        Ok(value) => println!("{}", value.written()),
+        Err(err) => eprintln!("{}", err.render(input)),
+    }
+    true
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
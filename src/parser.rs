@@ -1,14 +1,17 @@
-use std::collections::HashSet;
+use std::{collections::HashMap, fmt};
 
 use crate::ast::*;
 use crate::lexer::Error as LError;
-use crate::token::Token;
+use crate::span::{Span, Spanned};
+use crate::token::{Token, TokenKind};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Error {
     LexerError(LError),
-    UnbalancedParens,
-    DisallowedQuoting,
+    UnbalancedParens(Span),
+    MismatchedDelimiter(Span),
+    UnpairedMapKey(Span),
+    DanglingQuote(Span),
 }
 
 type Item = Result<Token, LError>;
@@ -17,12 +20,36 @@ type AstResult = Result<TopLevel, Error>;
 // list_stack is a stack of lists that we've encountered so far. When a '('
 // is encountered, level is bumped up and a new list is pushed onto the stack.
 // When a ')' is encountered, level is bumped down and the current list is
-// popped from the stack and appended to the parent list.
+// popped from the stack and appended to the parent list. Vectors, maps and
+// sets work the same way. open_spans holds the delimiter that opened each list
+// on the stack, and its span.
+// quote_levels holds the quotes (and quasiquotes, unquotes) that are waiting
+// for a datum on each level. There can be several of them: ''a is
+// (quote (quote a)).
 pub struct Parser<I: Iterator<Item = Item>> {
     tokens: I,
     list_stack: Vec<Vec<Expr>>,
+    open_spans: Vec<(Delimiter, Span)>,
     level: u64,
-    quote_levels: HashSet<u64>
+    quote_levels: HashMap<u64, Vec<(QuoteKind, Span)>>
+}
+
+#[derive(Clone, Copy)]
+enum Delimiter {
+    Paren,
+    Bracket,
+    Brace,
+    HashBrace,
+}
+
+#[derive(Clone, Copy)]
+enum QuoteKind {
+    Quote,
+    Quasiquote,
+    Unquote,
+    UnquoteSplicing,
+    // Drops its datum, see `quote_if_needed`
+    DatumComment,
 }
 
 impl<I: Iterator<Item = Item>> Parser<I> {
@@ -30,8 +57,9 @@ impl<I: Iterator<Item = Item>> Parser<I> {
         Parser {
             tokens,
             list_stack: Vec::new(),
+            open_spans: Vec::new(),
             level: 0,
-            quote_levels: HashSet::new()
+            quote_levels: HashMap::new()
         }
     }
 
@@ -44,15 +72,29 @@ impl<I: Iterator<Item = Item>> Parser<I> {
         // seemed to be the only reasonable one that worked 
         while let Some(token) = self.tokens.next() {
             match token {
-                Ok(token) => {
-                    if let Some(err) = match token {
-                        Token::LParen => self.lparen(),
-                        Token::RParen => self.rparen(),
-                        Token::Quote => self.quote(),
-                        Token::Identifier(str) => self.ident(str),
-                        Token::Integer(str) => self.int(str),
-                        Token::String(str) => self.str(str),
-                        Token::Boolean(val) => self.bool(val),
+                Ok(Token { kind, span }) => {
+                    if let Some(err) = match kind {
+                        TokenKind::LParen => self.open(Delimiter::Paren, span),
+                        TokenKind::RParen => self.close(')', span),
+                        TokenKind::LBracket => self.open(Delimiter::Bracket, span),
+                        TokenKind::RBracket => self.close(']', span),
+                        TokenKind::LBrace => self.open(Delimiter::Brace, span),
+                        TokenKind::HashLBrace => self.open(Delimiter::HashBrace, span),
+                        TokenKind::RBrace => self.close('}', span),
+                        TokenKind::Quote => self.quote(QuoteKind::Quote, span),
+                        TokenKind::Quasiquote => self.quote(QuoteKind::Quasiquote, span),
+                        TokenKind::Unquote => self.quote(QuoteKind::Unquote, span),
+                        TokenKind::UnquoteSplicing => self.quote(QuoteKind::UnquoteSplicing, span),
+                        TokenKind::DatumComment => self.quote(QuoteKind::DatumComment, span),
+                        TokenKind::Comment(_) => None,
+                        TokenKind::Identifier(str) => self.ident(str, span),
+                        TokenKind::Keyword(str) => self.keyword(str, span),
+                        TokenKind::Integer(str) => self.int(str, span),
+                        TokenKind::Float(str) => self.float(str, span),
+                        TokenKind::Ratio(str) => self.ratio(str, span),
+                        TokenKind::String(str) => self.str(str, span),
+                        TokenKind::Char(val) => self.char(val, span),
+                        TokenKind::Boolean(val) => self.bool(val, span),
                     } {
                         return Err(err);
                     }
@@ -63,93 +105,192 @@ impl<I: Iterator<Item = Item>> Parser<I> {
             }
         }
         
-        if self.level != 0 {
-            return Err(Error::UnbalancedParens);
+        if let Some(&(_, span)) = self.open_spans.last() {
+            return Err(Error::UnbalancedParens(span));
+        }
+        if let Some(span) = self.dangling_quote() {
+            return Err(Error::DanglingQuote(span));
         }
 
         // NOTE: Emtpy top-level expressions are supported.
         Ok(TopLevel(self.list_stack.pop().unwrap()))
     }
 
-    fn lparen(&mut self) -> Option<Error> {
+    fn open(&mut self, delimiter: Delimiter, span: Span) -> Option<Error> {
         self.level += 1;
         self.list_stack.push(Vec::new());
+        self.open_spans.push((delimiter, span));
         None
     }
-    fn rparen(&mut self) -> Option<Error> {
-        if self.level > 0 {
-            self.level -= 1;
+    fn close(&mut self, closer: char, span: Span) -> Option<Error> {
+        if let Some(quote) = self.dangling_quote() {
+            return Some(Error::DanglingQuote(quote));
+        }
+        let Some(&(delimiter, open)) = self.open_spans.last() else {
+            return Some(Error::UnbalancedParens(span));
+        };
+        if delimiter.closer() != closer {
+            return Some(Error::MismatchedDelimiter(span));
+        }
+        self.level -= 1;
+        self.open_spans.pop();
 
-            let current = self.list_stack.pop().unwrap();
-            let value = self.quote_if_needed(Expr::List(current));
-            let parent = self.list_stack.last_mut().unwrap();
-            parent.push(value);
+        let current = self.list_stack.pop().unwrap();
+        let span = open.to(span);
+        let kind = match delimiter {
+            Delimiter::Paren => ExprKind::List(current),
+            Delimiter::Bracket => ExprKind::Vector(current),
+            Delimiter::Brace if current.len() % 2 == 1 => return Some(Error::UnpairedMapKey(span)),
+            Delimiter::Brace => ExprKind::Map(current),
+            Delimiter::HashBrace => ExprKind::Set(current),
+        };
+        let value = self.quote_if_needed(Expr::new(kind, span));
+        let parent = self.list_stack.last_mut().unwrap();
+        parent.extend(value);
 
-            None
-        } else {
-            Some(Error::UnbalancedParens)
-        }
+        None
     }
-    fn quote(&mut self) -> Option<Error> {
-        self.quote_levels.insert(self.level);
+    fn quote(&mut self, kind: QuoteKind, span: Span) -> Option<Error> {
+        self.quote_levels.entry(self.level).or_default().push((kind, span));
         None
     }
 
-    fn ident(&mut self, str: String) -> Option<Error> {
-        let value = self.quote_if_needed(Expr::Ident(str));
-        self.list_stack.last_mut().unwrap().push(value);
+    fn ident(&mut self, str: String, span: Span) -> Option<Error> {
+        self.push(Expr::new(ExprKind::Ident(str), span))
+    }
+    fn keyword(&mut self, str: String, span: Span) -> Option<Error> {
+        self.push(Expr::new(ExprKind::Keyword(str), span))
+    }
+    fn int(&mut self, str: String, span: Span) -> Option<Error> {
+        let int = str.parse().unwrap();
+        self.push(Expr::new(ExprKind::Int(int), span))
+    }
+    fn float(&mut self, str: String, span: Span) -> Option<Error> {
+        let float = str.parse().unwrap();
+        self.push(Expr::new(ExprKind::Float(float), span))
+    }
+    fn ratio(&mut self, str: String, span: Span) -> Option<Error> {
+        let ratio = str.parse().unwrap();
+        self.push(Expr::new(ExprKind::Ratio(ratio), span))
+    }
+    fn str(&mut self, str: String, span: Span) -> Option<Error> {
+        self.push(Expr::new(ExprKind::Str(str), span))
+    }
+    fn char(&mut self, val: char, span: Span) -> Option<Error> {
+        self.push(Expr::new(ExprKind::Char(val), span))
+    }
+    fn bool(&mut self, val: bool, span: Span) -> Option<Error> {
+        self.push(Expr::new(ExprKind::Bool(val), span))
+    }
+
+    fn push(&mut self, expr: Expr) -> Option<Error> {
+        let value = self.quote_if_needed(expr);
+        self.list_stack.last_mut().unwrap().extend(value);
         None
     }
-    fn int(&mut self, str: String) -> Option<Error> {
-        if self.should_quote() {
-            Some(Error::DisallowedQuoting)
-        } else {
-            let int = str.parse().unwrap();
-            self.list_stack.last_mut().unwrap().push(Expr::Int(int));
-            None
+
+    // Wraps `expr` in all of the quotes pending at the current level, the
+    // nearest one innermost. A datum comment drops the expression instead, and
+    // leaves the quotes before it waiting for the next one: '#;a b is 'b.
+    fn quote_if_needed(&mut self, expr: Expr) -> Option<Expr> {
+        let mut quotes = self.quote_levels.remove(&self.level).unwrap_or_default();
+        let mut expr = expr;
+        while let Some((kind, quote)) = quotes.pop() {
+            let span = quote.to(expr.span);
+            let boxed = Box::new(expr);
+            let kind = match kind {
+                QuoteKind::Quote => ExprKind::Quoted(boxed),
+                QuoteKind::Quasiquote => ExprKind::Quasiquoted(boxed),
+                QuoteKind::Unquote => ExprKind::Unquoted(boxed),
+                QuoteKind::UnquoteSplicing => ExprKind::UnquoteSpliced(boxed),
+                QuoteKind::DatumComment => {
+                    if !quotes.is_empty() {
+                        self.quote_levels.insert(self.level, quotes);
+                    }
+                    return None;
+                }
+            };
+            expr = Expr::new(kind, span);
         }
+        Some(expr)
+    }
+
+    // A quote at the current level that has no datum to apply to, because the
+    // level is about to end.
+    fn dangling_quote(&self) -> Option<Span> {
+        self.quote_levels
+            .get(&self.level)
+            .and_then(|quotes| quotes.last())
+            .map(|&(_, span)| span)
     }
-    fn str(&mut self, str: String) -> Option<Error> {
-        if self.should_quote() {
-            Some(Error::DisallowedQuoting)
-        } else {
-            self.list_stack.last_mut().unwrap().push(Expr::Str(str));
-            None
+}
+
+impl Delimiter {
+    fn closer(self) -> char {
+        match self {
+            Self::Paren => ')',
+            Self::Bracket => ']',
+            Self::Brace | Self::HashBrace => '}',
         }
     }
-    fn bool(&mut self, val: bool) -> Option<Error> {
-        if self.should_quote() {
-            Some(Error::DisallowedQuoting)
-        } else {
-            self.list_stack.last_mut().unwrap().push(Expr::Bool(val));
-            None
+}
+
+// -------------------------------------------------------------------------- //
+// Trait implementations                                                      //
+// -------------------------------------------------------------------------- //
+
+impl Error {
+    // Whether `source` failed to parse only because it ended too early, i.e.
+    // inside of a list (or vector, map, set) or a string. Used to keep reading
+    // input interactively.
+    pub fn is_incomplete(&self, source: &str) -> bool {
+        match *self {
+            Self::LexerError(LError::UnclosedString(_) | LError::UnclosedComment(_)) => true,
+            Self::UnbalancedParens(span) => {
+                matches!(source.get(span.start..span.end), Some("(" | "[" | "{" | "#{"))
+            }
+            Self::DanglingQuote(span) => source[span.end..].trim().is_empty(),
+            _ => false,
         }
     }
+}
 
-    fn should_quote(&mut self) -> bool {
-        let level = &self.level;
-        if self.quote_levels.contains(level) {
-            self.quote_levels.remove(level);
-            true
-        } else {
-            false
+impl Spanned for Error {
+    fn span(&self) -> Option<Span> {
+        match *self {
+            Self::LexerError(err) => err.span(),
+            Self::UnbalancedParens(span)
+            | Self::MismatchedDelimiter(span)
+            | Self::UnpairedMapKey(span)
+            | Self::DanglingQuote(span) => Some(span),
         }
     }
+}
 
-    fn quote_if_needed(&mut self, expr: Expr) -> Expr {
-        if self.should_quote() {
-            Expr::Quoted(Box::new(expr))
-        } else {
-            expr
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LexerError(err) => err.fmt(f),
+            Self::UnbalancedParens(span) => write!(f, "unbalanced parenthesis at {span}"),
+            Self::MismatchedDelimiter(span) => write!(f, "mismatched closing delimiter at {span}"),
+            Self::UnpairedMapKey(span) => write!(f, "map literal with a key but no value at {span}"),
+            Self::DanglingQuote(span) => write!(f, "quote without a datum at {span}"),
         }
     }
 }
 
+impl std::error::Error for Error {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::token::test_macros::*;
-    use crate::ast::{Expr::*, TopLevel};
+    use crate::ast::TopLevel;
+
+    fn list(items: Vec<Expr>) -> Expr { ExprKind::List(items).into() }
+    fn quoted(expr: Expr) -> Expr { ExprKind::Quoted(Box::new(expr)).into() }
+    fn ident(name: &str) -> Expr { ExprKind::Ident(name.into()).into() }
+    fn int(value: i64) -> Expr { ExprKind::Int(value.into()).into() }
 
     macro_rules! parser_tests {
         ($($name:ident {[$($item:expr),*], $output:expr}),* $(,)?) => {
@@ -157,7 +298,7 @@ mod tests {
                 #[test]
                 fn $name() {
                     assert_eq!(
-                        Parser::new(vec![$(Ok($item)),*].into_iter()).parse(),
+                        Parser::new(vec![$(Ok(Token { kind: $item, span: Span::default() })),*].into_iter()).parse(),
                         $output
                     )
                 }
@@ -167,20 +308,20 @@ mod tests {
 
     #[rustfmt::skip]
     parser_tests! {
-        test_ok_1 { [int!("1")], Ok(TopLevel(vec![Int(1.into())]))},
+        test_ok_1 { [int!("1")], Ok(TopLevel(vec![int(1)]))},
         test_ok_2 { [lp!(), ident!("+"), int!("1"), int!("9"), rp!()], Ok(
             TopLevel(vec![
-                List(vec![
-                    Ident("+".into()),
-                    Int(1.into()),
-                    Int(9.into())
+                list(vec![
+                    ident("+"),
+                    int(1),
+                    int(9)
                 ])
             ])
         )},
         test_ok_3 { [int!("1"), int!("2")], Ok(
             TopLevel(vec![
-                Int(1.into()),
-                Int(2.into())
+                int(1),
+                int(2)
             ])
         )},
         test_ok_4 {
@@ -191,16 +332,16 @@ mod tests {
             ],
             Ok(
                 TopLevel(vec![
-                    List(vec![
-                        Ident("+".into()),
-                        List(vec![
-                            Ident("*".into()),
-                            Int(2.into()),
-                            Int(5.into())
+                    list(vec![
+                        ident("+"),
+                        list(vec![
+                            ident("*"),
+                            int(2),
+                            int(5)
                         ]),
-                        Int(9.into())
+                        int(9)
                     ]),
-                    Int(1.into())
+                    int(1)
                 ])
             )
         },
@@ -210,10 +351,10 @@ mod tests {
             ],
             Ok(
                 TopLevel(vec![
-                    List(vec![
-                        Ident("def".into()),
-                        Quoted(Box::new(Ident("a".into()))),
-                        Int(10.into())
+                    list(vec![
+                        ident("def"),
+                        quoted(ident("a")),
+                        int(10)
                     ])
                 ])
             )
@@ -225,19 +366,77 @@ mod tests {
             ],
             Ok(
                 TopLevel(vec![
-                    List(vec![
-                        Ident("def".into()),
-                        Quoted(Box::new(Ident("a".into()))),
-                        Quoted(Box::new(List(vec![
-                            Int(1.into()),
-                            Int(2.into()),
-                            Int(3.into()),
-                        ])))
+                    list(vec![
+                        ident("def"),
+                        quoted(ident("a")),
+                        quoted(list(vec![
+                            int(1),
+                            int(2),
+                            int(3),
+                        ]))
                     ])
                 ])
             )
         },
-        test_err_1 { [lp!(), int!("2")], Err(Error::UnbalancedParens)},
-        test_err_2 { [int!("2"), rp!()], Err(Error::UnbalancedParens)},
+        test_ok_7_quoting_literals {
+            [q!(), int!("1"), q!(), str!("a"), q!(), bool!(true)],
+            Ok(TopLevel(vec![
+                quoted(int(1)),
+                quoted(ExprKind::Str("a".into()).into()),
+                quoted(ExprKind::Bool(true).into()),
+            ]))
+        },
+        test_ok_8_nested_quotes {
+            [q!(), q!(), ident!("a"), lp!(), q!(), q!(), q!(), lp!(), rp!(), ident!("b"), rp!()],
+            Ok(TopLevel(vec![
+                quoted(quoted(ident("a"))),
+                list(vec![
+                    quoted(quoted(quoted(list(vec![])))),
+                    ident("b")
+                ])
+            ]))
+        },
+        test_ok_9_quasiquoting {
+            [qq!(), lp!(), ident!("a"), uq!(), ident!("b"), uqs!(), q!(), ident!("c"), rp!()],
+            Ok(TopLevel(vec![
+                ExprKind::Quasiquoted(Box::new(list(vec![
+                    ident("a"),
+                    ExprKind::Unquoted(Box::new(ident("b"))).into(),
+                    ExprKind::UnquoteSpliced(Box::new(quoted(ident("c")))).into(),
+                ]))).into()
+            ]))
+        },
+        test_ok_10_datum_comments {
+            [dc!(), lp!(), ident!("a"), rp!(), q!(), dc!(), int!("1"), int!("2"), lp!(), dc!(), dc!(), int!("3"), int!("4"), rp!(), comment!("; c")],
+            Ok(TopLevel(vec![
+                quoted(int(2)),
+                list(vec![]),
+            ]))
+        },
+        test_ok_11_maps_and_sets {
+            [lb!(), ident!("a"), int!("1"), rb!(), q!(), hlb!(), int!("1"), lb!(), rb!(), rb!()],
+            Ok(TopLevel(vec![
+                ExprKind::Map(vec![ident("a"), int(1)]).into(),
+                quoted(ExprKind::Set(vec![int(1), ExprKind::Map(vec![]).into()]).into()),
+            ]))
+        },
+        test_ok_12_vectors {
+            [lbk!(), int!("1"), lbk!(), rbk!(), rbk!(), q!(), lbk!(), ident!("a"), rbk!()],
+            Ok(TopLevel(vec![
+                ExprKind::Vector(vec![int(1), ExprKind::Vector(vec![]).into()]).into(),
+                quoted(ExprKind::Vector(vec![ident("a")]).into()),
+            ]))
+        },
+        test_err_1 { [lp!(), int!("2")], Err(Error::UnbalancedParens(Span::default()))},
+        test_err_2 { [int!("2"), rp!()], Err(Error::UnbalancedParens(Span::default()))},
+        test_err_3_dangling_quote { [lp!(), ident!("a"), q!(), rp!()], Err(Error::DanglingQuote(Span::default()))},
+        test_err_4_dangling_quote { [int!("1"), q!()], Err(Error::DanglingQuote(Span::default()))},
+        test_err_5_dangling_datum_comment { [lp!(), dc!(), rp!()], Err(Error::DanglingQuote(Span::default()))},
+        test_err_6_mismatched_delimiter { [lp!(), int!("1"), rb!()], Err(Error::MismatchedDelimiter(Span::default()))},
+        test_err_7_mismatched_delimiter { [hlb!(), rp!()], Err(Error::MismatchedDelimiter(Span::default()))},
+        test_err_8_unpaired_map_key { [lb!(), int!("1"), rb!()], Err(Error::UnpairedMapKey(Span::default()))},
+        test_err_9_unclosed_set { [hlb!(), int!("1")], Err(Error::UnbalancedParens(Span::default()))},
+        test_err_10_mismatched_bracket { [lp!(), rbk!()], Err(Error::MismatchedDelimiter(Span::default()))},
+        test_err_11_mismatched_bracket { [lbk!(), lp!(), rbk!(), rp!()], Err(Error::MismatchedDelimiter(Span::default()))},
     }
 }
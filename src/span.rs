@@ -0,0 +1,68 @@
+use std::fmt;
+
+// A region of source text. `start` and `end` are byte offsets (end exclusive),
+// `line` and `column` are 1-based and refer to `start`. Columns count chars,
+// not bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    // Smallest span covering both `self` and `other`, assuming `self` starts
+    // first.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: self.end.max(other.end),
+            ..self
+        }
+    }
+
+    // Renders the line the span starts on with a caret underneath the spanned
+    // text, e.g.
+    //
+    //  --> 2:4
+    //   |
+    // 2 | (+ 1 "a")
+    //   |      ^^^
+    pub fn render(&self, source: &str) -> String {
+        let line = source.lines().nth(self.line.saturating_sub(1)).unwrap_or("");
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+
+        // Underline at most up to the end of the starting line
+        let offset: usize = line.chars().take(self.column.saturating_sub(1)).count();
+        let width = source
+            .get(self.start..self.end)
+            .map_or(1, |text| text.lines().next().unwrap_or("").chars().count())
+            .clamp(1, (line.chars().count() - offset).max(1));
+
+        format!(
+            "{gutter}--> {self}\n{gutter} |\n{line_number} | {line}\n{gutter} | {}{}",
+            " ".repeat(offset),
+            "^".repeat(width),
+        )
+    }
+}
+
+// Errors that can point at the source text they were raised for.
+pub trait Spanned: fmt::Display {
+    fn span(&self) -> Option<Span>;
+
+    // The error message followed by a snippet of `source` pointing at it.
+    fn render(&self, source: &str) -> String {
+        match self.span() {
+            Some(span) => format!("{self}\n{}", span.render(source)),
+            None => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
@@ -1,24 +1,64 @@
+use crate::span::Span;
+
+#[derive(Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
 #[derive(Debug, PartialEq)]
-pub enum Token {
+pub enum TokenKind {
     LParen,
     RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    // #{, opens a set
+    HashLBrace,
     Quote,
+    Quasiquote,
+    Unquote,
+    UnquoteSplicing,
+    // #; comments out the datum that follows it
+    DatumComment,
+    // Only produced by lexers created `with_comments`
+    Comment(String),
     Identifier(String),
+    // The name, without the colon
+    Keyword(String),
     Integer(String),
+    Float(String),
+    Ratio(String),
     String(String),
+    Char(char),
     Boolean(bool),
 }
 
 #[cfg(test)]
 #[rustfmt::skip]
 pub(crate) mod test_macros {
-    macro_rules! lp { () => { crate::token::Token::LParen } }
-    macro_rules! rp { () => { crate::token::Token::RParen } }
-    macro_rules! q  { () => { crate::token::Token::Quote  } }
-    macro_rules! ident { ($str:literal)  => { crate::token::Token::Identifier($str.to_string())   } }
-    macro_rules! int   { ($str:literal)  => { crate::token::Token::Integer($str.parse().unwrap()) } }
-    macro_rules! str   { ($str:literal)  => { crate::token::Token::String($str.to_string())       } }
-    macro_rules! bool  { ($bool:literal) => { crate::token::Token::Boolean($bool)                 } }
+    macro_rules! lp { () => { crate::token::TokenKind::LParen } }
+    macro_rules! rp { () => { crate::token::TokenKind::RParen } }
+    macro_rules! lbk { () => { crate::token::TokenKind::LBracket } }
+    macro_rules! rbk { () => { crate::token::TokenKind::RBracket } }
+    macro_rules! lb { () => { crate::token::TokenKind::LBrace } }
+    macro_rules! rb { () => { crate::token::TokenKind::RBrace } }
+    macro_rules! hlb { () => { crate::token::TokenKind::HashLBrace } }
+    macro_rules! q  { () => { crate::token::TokenKind::Quote  } }
+    macro_rules! qq { () => { crate::token::TokenKind::Quasiquote } }
+    macro_rules! uq { () => { crate::token::TokenKind::Unquote } }
+    macro_rules! uqs { () => { crate::token::TokenKind::UnquoteSplicing } }
+    macro_rules! dc { () => { crate::token::TokenKind::DatumComment } }
+    macro_rules! comment { ($str:literal) => { crate::token::TokenKind::Comment($str.to_string()) } }
+    macro_rules! ident { ($str:literal)  => { crate::token::TokenKind::Identifier($str.to_string())   } }
+    macro_rules! kw    { ($str:literal)  => { crate::token::TokenKind::Keyword($str.to_string())      } }
+    macro_rules! int   { ($str:literal)  => { crate::token::TokenKind::Integer($str.parse().unwrap()) } }
+    macro_rules! float { ($str:literal)  => { crate::token::TokenKind::Float($str.to_string())        } }
+    macro_rules! ratio { ($str:literal)  => { crate::token::TokenKind::Ratio($str.to_string())        } }
+    macro_rules! str   { ($str:literal)  => { crate::token::TokenKind::String($str.to_string())       } }
+    macro_rules! chr   { ($chr:literal)  => { crate::token::TokenKind::Char($chr)                    } }
+    macro_rules! bool  { ($bool:literal) => { crate::token::TokenKind::Boolean($bool)                 } }
 
-    pub(crate) use {lp, rp, q, ident, int, str, bool};
+    pub(crate) use {lp, rp, lbk, rbk, lb, rb, hlb, q, qq, uq, uqs, dc, comment, ident, kw, int, float, ratio, str, chr, bool};
 }
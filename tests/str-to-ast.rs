@@ -1,5 +1,11 @@
 use lisp_rs::{lexer::Lexer, parser::Parser};
-use lisp_rs::ast::{Expr::*, TopLevel};
+use lisp_rs::ast::{Expr, ExprKind, TopLevel};
+use lisp_rs::parser::Error;
+use lisp_rs::span::{Span, Spanned};
+
+fn list(items: Vec<Expr>) -> Expr { ExprKind::List(items).into() }
+fn ident(name: &str) -> Expr { ExprKind::Ident(name.into()).into() }
+fn int(value: i64) -> Expr { ExprKind::Int(value.into()).into() }
 
 macro_rules! str_to_ast_tests {
     ($($name:ident {$input:expr, $output:expr}),+ $(,)?) => {
@@ -17,13 +23,13 @@ macro_rules! str_to_ast_tests {
 
 #[rustfmt::skip]
 str_to_ast_tests! {
-    test1 { "1", Ok(TopLevel(vec![Int(1.into())]))},
+    test1 { "1", Ok(TopLevel(vec![int(1)]))},
     test2 { "(+ 1 9)", Ok(
         TopLevel(vec![
-            List(vec![
-                Ident("+".into()),
-                Int(1.into()),
-                Int(9.into())
+            list(vec![
+                ident("+"),
+                int(1),
+                int(9)
             ])
         ])
     )},
@@ -32,16 +38,62 @@ str_to_ast_tests! {
             9)
          1",
         Ok(TopLevel(vec![
-            List(vec![
-                Ident("+".into()),
-                List(vec![
-                    Ident("*".into()),
-                    Int(2.into()),
-                    Int(5.into())
+            list(vec![
+                ident("+"),
+                list(vec![
+                    ident("*"),
+                    int(2),
+                    int(5)
                 ]),
-                Int(9.into())
+                int(9)
             ]),
-            Int(1.into())
+            int(1)
         ])
     )},
 }
+
+
+#[test]
+fn test_expr_spans() {
+    let source = "(+ 1\n   (* 2 3))";
+    let TopLevel(exprs) = Parser::new(Lexer::from(source)).parse().unwrap();
+    let ExprKind::List(items) = &exprs[0].kind else { panic!() };
+
+    assert_eq!(exprs[0].span, Span { start: 0, end: 16, line: 1, column: 1 });
+    assert_eq!(items[1].span, Span { start: 3, end: 4, line: 1, column: 4 });
+    assert_eq!(items[2].span, Span { start: 8, end: 15, line: 2, column: 4 });
+    assert_eq!(&source[items[2].span.start..items[2].span.end], "(* 2 3)");
+}
+
+#[test]
+fn test_error_spans() {
+    let source = "(+ 1 2)\n(* (+ 1 2)\n   3";
+    let err = Parser::new(Lexer::from(source)).parse().unwrap_err();
+    assert_eq!(err, Error::UnbalancedParens(Span { start: 8, end: 9, line: 2, column: 1 }));
+    assert_eq!(
+        err.render(source),
+        "unbalanced parenthesis at 2:1\n --> 2:1\n  |\n2 | (* (+ 1 2)\n  | ^"
+    );
+
+    let err = Parser::new(Lexer::from("(a \"b)")).parse().unwrap_err();
+    assert_eq!(err.span(), Some(Span { start: 3, end: 6, line: 1, column: 4 }));
+}
+
+#[test]
+fn test_incomplete_input() {
+    let is_incomplete = |source| match Parser::new(Lexer::from(source)).parse() {
+        Ok(_) => false,
+        Err(err) => err.is_incomplete(source),
+    };
+    assert!(is_incomplete("(+ 1"));
+    assert!(is_incomplete("(a (b c)\n (d"));
+    assert!(is_incomplete("(print \"abc"));
+    assert!(is_incomplete("(a) '"));
+    assert!(is_incomplete("#| unfinished"));
+    assert!(is_incomplete("(a) #;"));
+    assert!(is_incomplete("{'a #{1"));
+    assert!(is_incomplete("[1 [2"));
+    assert!(!is_incomplete("(+ 1))"));
+    assert!(!is_incomplete("(+ 1}"));
+    assert!(!is_incomplete("[1)"));
+}
@@ -1,6 +1,10 @@
-use lisp_rs::{lexer::Lexer, parser::Parser, eval::evaluator::evaluate_toplevel};
+use lisp_rs::{lexer::Lexer, parser::Parser, eval::evaluator::{eval_toplevel, evaluate_toplevel}};
+use lisp_rs::eval::env::Env;
+use lisp_rs::eval::error::{Arity, Error, ErrorKind};
 use lisp_rs::eval::value::{Value};
+use lisp_rs::span::{Span, Spanned};
 use num_bigint::BigInt;
+use std::rc::Rc;
 
 #[test]
 fn omg_it_works_for_ints() {
@@ -14,14 +18,14 @@ fn omg_it_works_for_strings() {
     let token_iter = Lexer::from(r#"(+ "5" (+ "1" "2" "3" "4" "5"))"#);
     let ast = Parser::new(token_iter).parse().unwrap();
     let value = evaluate_toplevel(ast);
-    assert!(matches!(value, Ok(Value::Str(v)) if v == String::from("512345")));
+    assert!(matches!(value, Ok(Value::Str(v)) if v == "512345"));
 }
 #[test]
 fn omg_errors_work_too() {
     let token_iter = Lexer::from(r#"(+ "5" (+ "1" "2" "3" "4" 5))"#);
     let ast = Parser::new(token_iter).parse().unwrap();
     let value = evaluate_toplevel(ast);
-    assert!(matches!(value, Err(())));
+    assert!(matches!(value, Err(Error { kind: ErrorKind::TypeError { .. }, .. })));
 }
 
 #[test]
@@ -46,4 +50,937 @@ fn test_def_string_mul_and_cmp() {
     let ast = Parser::new(token_iter).parse().unwrap();
     let value = evaluate_toplevel(ast);
     assert!(matches!(value, Ok(Value::Bool(true))));
-}
\ No newline at end of file
+}
+#[test]
+fn test_lambda_call() {
+    let token_iter = Lexer::from(
+        "(def 'add3 (lambda (a b c) (+ a b c)))
+         (add3 1 2 3)"
+    );
+    let ast = Parser::new(token_iter).parse().unwrap();
+    let value = evaluate_toplevel(ast);
+    assert!(matches!(value, Ok(Value::Int(v)) if v == BigInt::from(6)));
+}
+
+#[test]
+fn test_lambda_closure() {
+    let token_iter = Lexer::from(
+        "(def 'adder (fn (n) (fn (x) (+ x n))))
+         (def 'add5 (adder 5))
+         (def 'n 100)
+         (add5 10)"
+    );
+    let ast = Parser::new(token_iter).parse().unwrap();
+    let value = evaluate_toplevel(ast);
+    assert!(matches!(value, Ok(Value::Int(v)) if v == BigInt::from(15)));
+}
+
+#[test]
+fn test_lambda_immediate_call_and_arity() {
+    let token_iter = Lexer::from("((lambda (x) (* x x)) 7)");
+    let ast = Parser::new(token_iter).parse().unwrap();
+    let value = evaluate_toplevel(ast);
+    assert!(matches!(value, Ok(Value::Int(v)) if v == BigInt::from(49)));
+
+    let token_iter = Lexer::from("((lambda (x) x) 1 2)");
+    let ast = Parser::new(token_iter).parse().unwrap();
+    let value = evaluate_toplevel(ast);
+    assert!(matches!(
+        value,
+        Err(Error { kind: ErrorKind::ArityMismatch { expected: Arity::Exactly(1), got: 2 }, .. })
+    ));
+}
+
+fn eval(source: &str) -> Result<Value, Error> {
+    let ast = Parser::new(Lexer::from(source)).parse().unwrap();
+    evaluate_toplevel(ast)
+}
+
+fn int(value: i64) -> Value {
+    Value::Int(BigInt::from(value))
+}
+
+#[test]
+fn test_if() {
+    assert_eq!(eval("(if true 1 2)"), Ok(int(1)));
+    assert_eq!(eval("(if false 1 2)"), Ok(int(2)));
+    assert_eq!(eval("(if (< 1 2) 1)"), Ok(int(1)));
+    assert!(matches!(eval("(if false 1)"), Ok(Value::None)));
+    // Only the taken branch is evaluated
+    assert_eq!(eval("(if true 1 (undefined))"), Ok(int(1)));
+}
+
+#[test]
+fn test_recursion() {
+    assert_eq!(
+        eval(
+            "(def 'count (lambda (n acc) (if (= n acc) acc (count n (+ acc 1)))))
+             (count 10 0)"
+        ),
+        Ok(int(10))
+    );
+}
+
+#[test]
+fn test_cond_when_unless() {
+    let src = "(def 'sign (lambda (n) (cond ((< n 0) 'negative) ((> n 0) 'positive) (else 'zero))))";
+    assert_eq!(eval(&format!("{src} (sign 5)")), Ok(Value::Symbol("positive".into())));
+    assert_eq!(eval(&format!("{src} (sign 0)")), Ok(Value::Symbol("zero".into())));
+    assert_eq!(eval("(cond (false 1) (5))"), Ok(int(5)));
+    assert_eq!(eval("(when (= 1 1) 1 2)"), Ok(int(2)));
+    assert!(matches!(eval("(when false (undefined))"), Ok(Value::None)));
+    assert_eq!(eval("(unless false 1 2)"), Ok(int(2)));
+}
+
+#[test]
+fn test_and_or_short_circuit() {
+    assert_eq!(eval("(and)"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(or)"), Ok(Value::Bool(false)));
+    assert_eq!(eval("(and 1 2 3)"), Ok(int(3)));
+    assert_eq!(eval("(and 1 false (undefined))"), Ok(Value::Bool(false)));
+    assert_eq!(eval("(or false 2 (undefined))"), Ok(int(2)));
+}
+
+#[test]
+fn test_let_family() {
+    assert_eq!(eval("(def 'x 1) (let ((x 2) (y x)) (+ x y))"), Ok(int(3)));
+    assert_eq!(eval("(def 'x 1) (let* ((x 2) (y x)) (+ x y))"), Ok(int(4)));
+    assert_eq!(
+        eval(
+            "(letrec ((even? (fn (n k) (if (= n k) true (odd? n (+ k 1)))))
+                      (odd?  (fn (n k) (if (= n k) false (even? n (+ k 1))))))
+               (even? 10 0))"
+        ),
+        Ok(Value::Bool(true))
+    );
+    assert_eq!(eval("(def 'x 1) (let ((x 2)) x) x"), Ok(int(1)));
+}
+
+#[test]
+fn test_begin_and_quote() {
+    assert_eq!(eval("(begin (def 'a 1) (+ a 1))"), Ok(int(2)));
+    assert_eq!(eval("(quote abc)"), Ok(Value::Symbol("abc".into())));
+    assert_eq!(eval("(quote 5)"), Ok(int(5)));
+}
+
+#[test]
+fn test_lexical_scope() {
+    // `get-x` sees the `x` of where it was defined, not of where it is called
+    assert_eq!(
+        eval(
+            "(def 'x 1)
+             (def 'get-x (fn () x))
+             (let ((x 2)) (get-x))"
+        ),
+        Ok(int(1))
+    );
+    // Later top-level definitions are visible to earlier closures
+    assert_eq!(eval("(def 'f (fn () y)) (def 'y 5) (f)"), Ok(int(5)));
+}
+
+#[test]
+fn test_set() {
+    assert_eq!(eval("(def 'x 1) (let ((y 2)) (set! x 10)) x"), Ok(int(10)));
+    assert_eq!(eval("(def 'x 1) (let ((x 2)) (set! x 10)) x"), Ok(int(1)));
+    assert_eq!(
+        eval("(set! undefined 1)").unwrap_err().kind,
+        ErrorKind::Unbound("undefined".into())
+    );
+    assert_eq!(
+        eval(
+            "(def 'make-counter
+               (fn () (let ((n 0)) (fn () (set! n (+ n 1)) n))))
+             (def 'c1 (make-counter))
+             (def 'c2 (make-counter))
+             (c1) (c1) (c2)
+             (+ (c1) (c2))"
+        ),
+        Ok(int(5))
+    );
+}
+
+#[test]
+fn test_recursive_closures_are_freed() {
+    // Each of these lambdas is bound in the frame it closes over
+    for source in [
+        "(letrec ((g (fn (x) (g x)))) g)",
+        "(letrec ((even? (fn (n) (odd? n))) (odd? (fn (n) (even? n)))) even?)",
+        "((fn () (def 'h (fn (x) (h x))) h))",
+    ] {
+        let Ok(Value::Lambda(lambda)) = eval(source) else {
+            panic!("{source} didn't evaluate to a lambda");
+        };
+        let weak = Rc::downgrade(&lambda);
+        drop(lambda);
+        // Create enough frames for cycles to get collected
+        eval("(def 'count (fn (n) (if (= n 5000) n (count (+ n 1))))) (count 0)").unwrap();
+        assert!(weak.upgrade().is_none(), "{source} leaked");
+    }
+
+    // Recursive closures that are still referenced keep working
+    assert_eq!(
+        eval(
+            "(def 'countdown (letrec ((g (fn (n) (if (= n 0) 'done (g (- n 1)))))) g))
+             (def 'count (fn (n) (if (= n 5000) n (count (+ n 1)))))
+             (count 0)
+             (countdown 3)"
+        ),
+        Ok(Value::Symbol("done".into()))
+    );
+}
+
+fn span(start: usize, end: usize, line: usize, column: usize) -> Span {
+    Span { start, end, line, column }
+}
+
+fn err(source: &str) -> Error {
+    eval(source).unwrap_err()
+}
+
+#[test]
+fn test_error_kinds() {
+    assert_eq!(
+        err("(+ 1 \"a\")"),
+        Error {
+            kind: ErrorKind::TypeError { expected: "number", found: "string" },
+            function: Some("+".into()),
+            span: Some(span(0, 9, 1, 1)),
+        }
+    );
+    assert_eq!(
+        err("(def 'a)"),
+        Error {
+            kind: ErrorKind::ArityMismatch { expected: Arity::Exactly(2), got: 1 },
+            function: Some("def".into()),
+            span: Some(span(0, 8, 1, 1)),
+        }
+    );
+    assert_eq!(
+        err("(+ 1 undefined)"),
+        Error {
+            kind: ErrorKind::Unbound("undefined".into()),
+            function: None,
+            span: Some(span(5, 14, 1, 6)),
+        }
+    );
+    assert_eq!(
+        err("(1 2)"),
+        Error {
+            kind: ErrorKind::NotCallable("int"),
+            function: None,
+            span: Some(span(0, 5, 1, 1)),
+        }
+    );
+    assert_eq!(
+        err("(def 'f (fn (x) x)) (f)"),
+        Error {
+            kind: ErrorKind::ArityMismatch { expected: Arity::Exactly(1), got: 0 },
+            function: Some("f".into()),
+            span: Some(span(20, 23, 1, 21)),
+        }
+    );
+    assert!(matches!(
+        err("(let (x 1) x)"),
+        Error {
+            kind: ErrorKind::InvalidSyntax(_),
+            function: Some(name),
+            span: Some(Span { start: 6, .. }),
+        } if name == "let"
+    ));
+}
+
+#[test]
+fn test_error_display() {
+    assert_eq!(
+        err("(> 1 \"a\")").to_string(),
+        "error in `>` at 1:1: type mismatch: expected number, found string"
+    );
+    assert_eq!(
+        err("(+ 1)").to_string(),
+        "error in `+` at 1:1: expected at least 2 arguments, got 1"
+    );
+    assert_eq!(err("nope").to_string(), "error at 1:1: unbound identifier `nope`");
+}
+
+#[test]
+fn test_error_render() {
+    let source = "(def 'x 1)\n(+ x\n   \"two\")";
+    assert_eq!(
+        err(source).render(source),
+        "error in `+` at 2:1: type mismatch: expected number, found string\n\
+         \x20--> 2:1\n\
+         \x20 |\n\
+         2 | (+ x\n\
+         \x20 | ^^^^"
+    );
+}
+
+#[test]
+fn test_load() {
+    let path = std::env::temp_dir().join(format!("lisp-rs-load-{}.lisp", std::process::id()));
+    std::fs::write(&path, "#!/usr/bin/env lisp-rs\n(def 'double (fn (x) (* x 2)))").unwrap();
+
+    let source = format!("(load {:?}) (double 21)", path.to_str().unwrap());
+    assert_eq!(eval(&source), Ok(int(42)));
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        err(&source).kind,
+        ErrorKind::LoadFailed { path: failed, .. } if failed == path.to_str().unwrap()
+    ));
+}
+
+#[test]
+fn test_script_args() {
+    let args = vec!["script.lisp".to_string(), "first".to_string()];
+    let ast = Parser::new(Lexer::from("(+ (argv 1) (* \"!\" argc))")).parse().unwrap();
+    let value = eval_toplevel(ast, &mut Env::with_args(args));
+    assert_eq!(value, Ok(Value::Str("first!!".into())));
+}
+
+fn show(source: &str) -> String {
+    eval(source).unwrap().to_string()
+}
+
+#[test]
+fn test_quoted_lists() {
+    assert_eq!(show("'(1 2 3)"), "(1 2 3)");
+    assert_eq!(show("'(a (b \"c\") ())"), "(a (b c) ())");
+    assert_eq!(show("(quote (+ 1 2))"), "(+ 1 2)");
+    assert_eq!(show("'()"), "()");
+}
+
+#[test]
+fn test_dotted_lists() {
+    assert_eq!(show("'(1 . 2)"), "(1 . 2)");
+    assert_eq!(show("(list (car '(1 . 2)) (cdr '(1 . 2)))"), "(1 2)");
+    assert_eq!(show("'(1 2 . (3 4))"), "(1 2 3 4)");
+    assert_eq!(show("(def 'x 3) `(1 ,(+ 1 1) . ,x)"), "(1 2 . 3)");
+    assert_eq!(show("(defmacro pair (a b) `(quote (,a . ,b))) (pair 1 2)"), "(1 . 2)");
+    for source in ["'(. 1)", "'(1 .)", "'(1 . 2 3)", "'(1 . 2 . 3)", "'[1 . 2]", "`(1 . 2 3)"] {
+        assert!(matches!(err(source).kind, ErrorKind::InvalidSyntax(_)), "{source}");
+    }
+}
+
+#[test]
+fn test_pairs() {
+    assert_eq!(show("(cons 1 2)"), "(1 . 2)");
+    assert_eq!(show("(cons 1 (cons 2 '()))"), "(1 2)");
+    assert_eq!(show("(car '(1 2 3))"), "1");
+    assert_eq!(show("(cdr '(1 2 3))"), "(2 3)");
+    assert_eq!(show("(list 1 (+ 1 1) 'three)"), "(1 2 three)");
+    assert_eq!(show("(list)"), "()");
+    assert_eq!(
+        err("(car '())").kind,
+        ErrorKind::TypeError { expected: "pair", found: "nil" }
+    );
+}
+
+#[test]
+fn test_list_predicates_and_length() {
+    assert_eq!(eval("(null? '())"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(null? '(1))"), Ok(Value::Bool(false)));
+    assert_eq!(eval("(pair? '(1))"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(pair? '())"), Ok(Value::Bool(false)));
+    assert_eq!(eval("(length '(1 2 3))"), Ok(int(3)));
+    assert_eq!(eval("(length '())"), Ok(int(0)));
+    assert_eq!(
+        err("(length (cons 1 2))").kind,
+        ErrorKind::TypeError { expected: "list", found: "pair" }
+    );
+}
+
+#[test]
+fn test_append_and_reverse() {
+    assert_eq!(show("(append '(1 2) '(3) '() '(4 5))"), "(1 2 3 4 5)");
+    assert_eq!(show("(append '(1) 2)"), "(1 . 2)");
+    assert_eq!(show("(append)"), "()");
+    assert_eq!(show("(reverse '(1 2 3))"), "(3 2 1)");
+}
+
+#[test]
+fn test_higher_order_list_functions() {
+    assert_eq!(show("(map (fn (x) (* x x)) '(1 2 3))"), "(1 4 9)");
+    assert_eq!(show("(map + '(1 2 3) '(10 20))"), "(11 22)");
+    assert_eq!(show("(filter (fn (x) (< x 3)) '(1 5 2 4))"), "(1 2)");
+    assert_eq!(show("(fold cons '() '(1 2 3))"), "(3 2 1)");
+    assert_eq!(eval("(fold + 0 '(1 2 3 4))"), Ok(int(10)));
+}
+
+#[test]
+fn test_code_as_data() {
+    assert_eq!(
+        show(
+            "(def 'sum-list (fn (xs) (if (null? xs) 0 (+ (car xs) (sum-list (cdr xs))))))
+             (list (sum-list '(1 2 3)) (car '((a b) c)))"
+        ),
+        "(6 (a b))"
+    );
+}
+
+#[test]
+fn test_quoted_literals_and_nested_quotes() {
+    assert_eq!(eval("'5"), Ok(int(5)));
+    assert_eq!(eval("'\"a\""), Ok(Value::Str("a".into())));
+    assert_eq!(eval("'true"), Ok(Value::Bool(true)));
+    assert_eq!(show("''x"), "(quote x)");
+    assert_eq!(show("'''x"), "(quote (quote x))");
+    assert_eq!(show("''(1 '2)"), "(quote (1 (quote 2)))");
+    assert_eq!(show("(car ''x)"), "quote");
+    assert_eq!(show("(quote 'x)"), "(quote x)");
+}
+
+#[test]
+fn test_quasiquote() {
+    assert_eq!(show("`(1 ,(+ 1 1) 3)"), "(1 2 3)");
+    assert_eq!(show("(def 'xs '(b c)) `(a ,@xs d)"), "(a b c d)");
+    assert_eq!(show("`(a ,@'() b)"), "(a b)");
+    assert_eq!(show("`x"), "x");
+    assert_eq!(show("`(1 '(2 ,(+ 1 2)))"), "(1 (quote (2 3)))");
+    assert_eq!(show("(def 'n 5) `(n is ,n)"), "(n is 5)");
+}
+
+#[test]
+fn test_nested_quasiquote() {
+    assert_eq!(show("`(a `(b ,(c ,(+ 1 2))))"), "(a (quasiquote (b (unquote (c 3)))))");
+    assert_eq!(show("'`(a ,b ,@c)"), "(quasiquote (a (unquote b) (unquote-splicing c)))");
+}
+
+#[test]
+fn test_unquote_errors() {
+    assert!(matches!(err(",a").kind, ErrorKind::InvalidSyntax(_)));
+    assert!(matches!(err("`,@'(1)").kind, ErrorKind::InvalidSyntax(_)));
+    assert_eq!(
+        err("`(1 ,@2)").kind,
+        ErrorKind::TypeError { expected: "list", found: "int" }
+    );
+}
+
+#[test]
+fn test_rest_parameters() {
+    assert_eq!(show("((fn (a . rest) (list a rest)) 1 2 3)"), "(1 (2 3))");
+    assert_eq!(show("((fn (a . rest) rest) 1)"), "()");
+    assert_eq!(show("((fn args args) 1 2)"), "(1 2)");
+    assert_eq!(
+        err("((fn (a b . rest) a) 1)").kind,
+        ErrorKind::ArityMismatch { expected: Arity::AtLeast(2), got: 1 }
+    );
+    assert!(matches!(err("(fn (a . b c) a)").kind, ErrorKind::InvalidSyntax(_)));
+}
+
+#[test]
+fn test_defmacro() {
+    let src = "(defmacro my-unless (test . body) `(if ,test false (begin ,@body)))";
+    assert_eq!(eval(&format!("{src} (my-unless false 1 2)")), Ok(int(2)));
+    assert_eq!(eval(&format!("{src} (my-unless true (undefined))")), Ok(Value::Bool(false)));
+
+    // Arguments are passed unevaluated, as data
+    assert_eq!(show("(defmacro quote-all args `',args) (quote-all a (b c))"), "(a (b c))");
+
+    // Macros expanding into other macros
+    assert_eq!(
+        eval(
+            "(defmacro inc! (name) `(set! ,name (+ ,name 1)))
+             (defmacro twice! (name) `(begin (inc! ,name) (inc! ,name)))
+             (def 'x 1)
+             (twice! x)
+             x"
+        ),
+        Ok(int(3))
+    );
+}
+
+#[test]
+fn test_macros_are_a_separate_namespace() {
+    assert_eq!(
+        eval("(defmacro m () 1) (def 'm 2) (+ (m) m)"),
+        Ok(int(3))
+    );
+    assert_eq!(err("(defmacro m () 1) m").kind, ErrorKind::Unbound("m".into()));
+}
+
+#[test]
+fn test_macroexpand() {
+    let src = "(defmacro my-inc (x) `(+ ,x 1))
+               (defmacro my-inc2 (x) `(my-inc (my-inc ,x)))";
+    assert_eq!(show(&format!("{src} (macroexpand-1 '(my-inc2 a))")), "(my-inc (my-inc a))");
+    assert_eq!(show(&format!("{src} (macroexpand '(my-inc2 a))")), "(+ (my-inc a) 1)");
+    assert_eq!(show(&format!("{src} (macroexpand '(other a))")), "(other a)");
+    assert_eq!(show("(macroexpand 5)"), "5");
+}
+
+#[test]
+fn test_syntax_rules() {
+    let swap = "(define-syntax swap!
+                  (syntax-rules ()
+                    ((_ a b) (let ((tmp a)) (set! a b) (set! b tmp)))))";
+    assert_eq!(show(&format!("{swap} (def 'x 1) (def 'y 2) (swap! x y) (list x y)")), "(2 1)");
+    // The `tmp` introduced by the template doesn't capture the user's `tmp`
+    assert_eq!(
+        show(&format!("{swap} (def 'tmp 1) (def 'y 2) (swap! tmp y) (list tmp y)")),
+        "(2 1)"
+    );
+
+    // Introduced names are only renamed in the scope that binds them
+    assert_eq!(
+        eval("(define-syntax m (syntax-rules () ((_ x) (begin (let ((y 1)) y) y))))
+              (def 'y 9)
+              (m 0)"),
+        Ok(int(9))
+    );
+    assert_eq!(
+        show("(define-syntax m (syntax-rules () ((_) (let ((y 1)) (list y 'y)))))
+              (m)"),
+        "(1 y)"
+    );
+    // Free introduced names mean what they mean at the top level, whatever the
+    // user binds around the macro
+    let my_list = "(define-syntax pair (syntax-rules () ((_ a b) (list a b))))
+                   (define-syntax bind
+                     (syntax-rules () ((_ name value body) (let ((name value)) (list body)))))
+                   (define-syntax my-if (syntax-rules () ((_ c a b) (if c a b))))";
+    assert_eq!(show(&format!("{my_list} (let ((list 0)) (pair 1 2))")), "(1 2)");
+    assert_eq!(show(&format!("{my_list} ((fn (list) (pair list 2)) 5)")), "(5 2)");
+    assert_eq!(show(&format!("{my_list} (bind list 1 list)")), "(1)");
+    assert_eq!(show(&format!("{my_list} (let ((if list)) (my-if false 1 2))")), "2");
+
+    // Ellipses, nested ones and tails after them
+    let my_let = "(define-syntax my-let
+                    (syntax-rules ()
+                      ((_ ((name value) ...) body ...) ((fn (name ...) body ...) value ...))))";
+    assert_eq!(eval(&format!("{my_let} (my-let ((a 1) (b 2)) (+ a b))")), Ok(int(3)));
+    assert_eq!(
+        show("(define-syntax rev (syntax-rules () ((_ x ... last) (list last x ...))))
+              (rev 1 2 3)"),
+        "(3 1 2)"
+    );
+
+    // Literals and the first matching rule wins
+    let my_cond = "(define-syntax my-cond
+                     (syntax-rules (else)
+                       ((_ (else e)) e)
+                       ((_ (c e) clause ...) (if c e (my-cond clause ...)))))";
+    assert_eq!(eval(&format!("{my_cond} (my-cond (false 1) (else 2))")), Ok(int(2)));
+    assert_eq!(eval(&format!("{my_cond} (my-cond (true 1) (else 2))")), Ok(int(1)));
+
+    // Dotted tails
+    assert_eq!(
+        show("(define-syntax my-list (syntax-rules () ((_ . items) (list . items))))
+              (my-list 1 2)"),
+        "(1 2)"
+    );
+}
+
+#[test]
+fn test_syntax_rules_scope_and_errors() {
+    let src = "(define-syntax one (syntax-rules () ((_) 1)))";
+    // Local bindings shadow syntax, quoted data is left alone
+    assert_eq!(eval(&format!("{src} (let ((one (fn () 2))) (one))")), Ok(int(2)));
+    assert_eq!(show(&format!("{src} '(one)")), "(one)");
+    // Syntax is usable from function bodies and macro expansions
+    assert_eq!(eval(&format!("{src} ((fn () (one)))")), Ok(int(1)));
+    assert_eq!(eval(&format!("{src} (defmacro m () '(one)) (m)")), Ok(int(1)));
+
+    let no_match = err(&format!("{src} (one 2)"));
+    assert!(matches!(no_match.kind, ErrorKind::InvalidSyntax(_)));
+    assert_eq!(no_match.function.as_deref(), Some("one"));
+    assert!(matches!(err("(define-syntax x 1)").kind, ErrorKind::InvalidSyntax(_)));
+
+    // Malformed rules are rejected when they are defined
+    for (rules, reason) in [
+        ("(() 1)", "a pattern must start with the macro keyword"),
+        ("((m ...) 1)", "`...` must follow the pattern it repeats"),
+        ("((m (...)) 1)", "`...` must follow the pattern it repeats"),
+        ("((m a ... b ...) 1)", "a list pattern can only contain one `...`"),
+    ] {
+        let error = err(&format!("(define-syntax m (syntax-rules () {rules})) (m 1 2)"));
+        assert_eq!(error.kind, ErrorKind::InvalidSyntax(reason.into()), "{rules}");
+    }
+}
+
+#[test]
+fn test_tail_calls_run_in_constant_stack() {
+    assert_eq!(
+        eval("(def 'count (fn (n) (if (= n 1000000) n (count (+ n 1))))) (count 0)"),
+        Ok(int(1000000))
+    );
+    // Mutual recursion, through and/or
+    assert_eq!(
+        eval(
+            "(letrec ((ping (fn (n) (or (= n 1000000) (pong (+ n 1)))))
+                      (pong (fn (n) (and true (ping (+ n 1))))))
+               (ping 0))"
+        ),
+        Ok(Value::Bool(true))
+    );
+    // Through cond, let, begin and when; far deeper than the stack allows
+    assert_eq!(
+        eval(
+            "(def 'count (fn (n acc)
+               (cond ((= n 100000) acc)
+                     (else (let ((next (+ n 1)))
+                             (begin (when false 0) (count next (+ acc 2))))))))
+             (count 0 0)"
+        ),
+        Ok(int(200000))
+    );
+}
+
+#[test]
+fn test_number_literals() {
+    assert_eq!(show("-5"), "-5");
+    assert_eq!(show("'(+1 -0 1.5 -2.5e3 1e-2)"), "(1 0 1.5 -2500.0 0.01)");
+    assert_eq!(show("1/3"), "1/3");
+    assert_eq!(show("-6/4"), "-3/2");
+    // Whole ratios are integers
+    assert_eq!(eval("4/2"), Ok(int(2)));
+    // A lone sign is still an identifier
+    assert_eq!(eval("(def '- 1) -"), Ok(int(1)));
+}
+
+#[test]
+fn test_numeric_contagion() {
+    // Exact results stay exact
+    assert_eq!(show("(+ 1/3 2/3)"), "1");
+    assert_eq!(show("(+ 1 1/2)"), "3/2");
+    assert_eq!(show("(* 2/3 3/4 -2)"), "-1");
+    // Floats are contagious
+    assert_eq!(show("(+ 1 0.5)"), "1.5");
+    assert_eq!(show("(* 1/2 1.0)"), "0.5");
+    assert_eq!(show("(+ 1 2.0)"), "3.0");
+
+    assert_eq!(eval("(= 1 1.0 2/2)"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(= 1/3 0.5)"), Ok(Value::Bool(false)));
+    assert_eq!(eval("(< 1/3 0.5)"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(> -1 -3/2)"), Ok(Value::Bool(true)));
+    assert_eq!(
+        err("(+ 1.5 true)").kind,
+        ErrorKind::TypeError { expected: "number", found: "bool" }
+    );
+}
+
+#[test]
+fn test_arithmetic_builtins() {
+    assert_eq!(show("(list (- 5) (- 10 1 2) (- 1/2 1) (- 1 0.5))"), "(-5 7 -1/2 0.5)");
+    assert_eq!(show("(list (/ 2) (/ 6 3) (/ 1 3) (/ 1 2.0) (/ 3/4 3))"), "(1/2 2 1/3 0.5 1/4)");
+    assert_eq!(show("(list (quotient 7 2) (quotient -7 2) (remainder -7 2) (modulo -7 2))"), "(3 -3 -1 1)");
+    assert_eq!(show("(list (abs -5) (abs -1/2) (abs -1.5))"), "(5 1/2 1.5)");
+    assert_eq!(show("(list (min 3 1 2) (max 1/2 1/3) (max 1 2.0) (min 1 2.0))"), "(1 1/2 2.0 1.0)");
+    assert_eq!(show("(list (expt 2 100) (expt 2 -2) (expt 2/3 2) (expt 4 0.5))"), "(1267650600228229401496703205376 1/4 4/9 2.0)");
+    assert_eq!(show("(list (expt 1 1000000000) (expt -1 -1000000001) (expt 0 1000000000))"), "(1 -1 0)");
+    for huge in ["(expt 2 1000000000)", "(expt 1/3 -100000000)", "(expt 10 (expt 2 40))"] {
+        assert_eq!(err(huge).kind, ErrorKind::InvalidArgument("exponent is too large".into()), "{huge}");
+    }
+    assert_eq!(show("(list (gcd 12 -18) (gcd) (lcm 4 6) (lcm) (lcm 0 5))"), "(6 0 12 1 0)");
+    assert_eq!(show("(list (sqrt 16) (sqrt 4/9) (sqrt 2.25) (sqrt 2))"), "(4 2/3 1.5 1.4142135623730951)");
+    assert_eq!(show("(exact->inexact 1/4)"), "0.25");
+    assert_eq!(show("(list (zero? 0) (zero? 0.0) (zero? 1/2) (even? 4) (odd? 4) (odd? -3))"), "(true true false true false true)");
+
+    for division in ["(/ 1 0)", "(/ 1/2 0)", "(quotient 1 0)", "(modulo 1 0)", "(expt 0 -1)"] {
+        assert_eq!(err(division).kind, ErrorKind::InvalidArgument("division by zero".into()));
+    }
+    assert_eq!(show("(/ 1 0.0)"), "inf");
+    assert_eq!(
+        err("(quotient 1.5 1)").kind,
+        ErrorKind::TypeError { expected: "int", found: "float" }
+    );
+    assert_eq!(
+        err("(min \"a\")").kind,
+        ErrorKind::TypeError { expected: "number", found: "string" }
+    );
+}
+
+#[test]
+fn test_chained_comparisons() {
+    assert_eq!(show("(list (< 1 2 3) (< 1 3 2) (> 3 2 1) (> 3 3 1))"), "(true false true false)");
+    assert_eq!(show("(list (<= 1 1 2) (<= 2 1) (>= 3 3 1) (>= 1 2))"), "(true false true false)");
+    assert_eq!(show("(list (= 1 1 1.0) (= 1 1 2) (!= 1 2 1) (!= 1 1))"), "(true false true false)");
+    assert_eq!(show("(list (< \"a\" \"b\" \"c\") (>= \"b\" \"a\"))"), "(true true)");
+    assert_eq!(
+        err("(< 1 \"a\")").kind,
+        ErrorKind::TypeError { expected: "number", found: "string" }
+    );
+}
+
+#[test]
+fn test_comments() {
+    assert_eq!(
+        eval(
+            "; a script
+             (def 'x 1) ; the answer
+             #| (def 'x 2)
+                #| nested |# |#
+             (+ x #;(undefined) #; 10 2)"
+        ),
+        Ok(int(3))
+    );
+    assert_eq!(show("'(a #;b c)"), "(a c)");
+}
+
+#[test]
+fn test_string_escapes() {
+    assert_eq!(eval(r#"(+ "say \"hi\"" "\n")"#), Ok(Value::Str("say \"hi\"\n".into())));
+    assert_eq!(eval(r##"#r#"C:\path "quoted""#"##), Ok(Value::Str(r#"C:\path "quoted""#.into())));
+    assert_eq!(
+        Parser::new(Lexer::from(r#""\x""#))
+            .parse()
+            .unwrap_err()
+            .to_string(),
+        "invalid escape sequence at 1:2"
+    );
+}
+
+#[test]
+fn test_unicode_identifiers() {
+    assert_eq!(
+        eval("(def '→ (fn (x) (+ x 1)))\u{a0}(def 'café 41)\u{2003}(→ café)"),
+        Ok(int(42))
+    );
+}
+
+#[test]
+fn test_chars() {
+    assert_eq!(eval(r"#\a"), Ok(Value::Char('a')));
+    assert_eq!(eval(r"'(#\space #\newline #\x41)"), eval(r#"(string->list " \nA")"#));
+    assert_eq!(show(r"(list (char->integer #\A) (integer->char 955))"), "(65 λ)");
+    assert_eq!(show(r"(list (char-upcase #\a) (char-upcase #\ß) (char-upcase #\1))"), "(A ß 1)");
+    assert_eq!(show(r"(list (char-alphabetic? #\λ) (char-alphabetic? #\1))"), "(true false)");
+    assert_eq!(eval(r#"(string-ref "aλb" 1)"#), Ok(Value::Char('λ')));
+    assert_eq!(eval(r"(list->string (list #\a #\λ))"), Ok(Value::Str("aλ".into())));
+
+    assert_eq!(
+        err(r#"(string-ref "ab" 2)"#).kind,
+        ErrorKind::InvalidArgument("index 2 is out of range".into())
+    );
+    assert_eq!(
+        err("(integer->char 55296)").kind,
+        ErrorKind::InvalidArgument("55296 is not a character code".into())
+    );
+    assert_eq!(
+        err("(list->string '(1))").kind,
+        ErrorKind::TypeError { expected: "char", found: "int" }
+    );
+}
+
+#[test]
+fn test_string_library() {
+    let str = |s: &str| Ok(Value::Str(s.into()));
+    assert_eq!(eval(r#"(list (string-length "aλb") (string-length "e\u{301}"))"#), eval("'(3 2)"));
+    assert_eq!(eval(r#"(substring "aλbc" 1 3)"#), str("λb"));
+    assert_eq!(eval(r#"(substring "aλbc" 2)"#), str("bc"));
+    assert_eq!(eval(r#"(list (string-index "aλbλ" #\λ) (string-index "aλbλ" "bλ") (string-index "ab" "c"))"#), eval("'(1 2 false)"));
+    assert_eq!(eval(r#"(string-split "a,b,,c" ",")"#), eval(r#"'("a" "b" "" "c")"#));
+    assert_eq!(eval(r#"(string-split "  a b\n c ")"#), eval(r#"'("a" "b" "c")"#));
+    assert_eq!(eval(r#"(string-join '("a" "b" "c") ", ")"#), str("a, b, c"));
+    assert_eq!(eval(r#"(string-join '("a" "b"))"#), str("ab"));
+    assert_eq!(eval(r#"(string-trim "\t ab c \n")"#), str("ab c"));
+    assert_eq!(eval(r#"(string-upcase "straße λ")"#), str("STRASSE Λ"));
+    assert_eq!(eval(r#"(string-downcase "ΑΒ c")"#), str("αβ c"));
+    assert_eq!(eval(r#"(list (string-contains? "hello" "ell") (string-contains? "hello" #\z))"#), eval("'(true false)"));
+    assert_eq!(eval(r#"(string-replace "a-b-c" "-" "+")"#), str("a+b+c"));
+    assert_eq!(eval(r#"(list (string->number "-12") (string->number "1.5e1") (string->number "6/4"))"#), eval("'(-12 15.0 3/2)"));
+    assert_eq!(eval(r#"(list (string->number "12a") (string->number "") (string->number "1 2"))"#), eval("'(false false false)"));
+    assert_eq!(
+        eval(r##"(list (string->number "1;x") (string->number " 12 ") (string->number "#| c |# 5"))"##),
+        eval("'(false false false)")
+    );
+    assert_eq!(eval("(number->string 3/4)"), str("3/4"));
+    assert_eq!(eval(r#"(symbol->string (string->symbol "λx"))"#), str("λx"));
+
+    assert_eq!(
+        err(r#"(substring "abc" 2 1)"#).kind,
+        ErrorKind::InvalidArgument("range 2..1 is out of bounds for a string of length 3".into())
+    );
+    assert_eq!(
+        err(r#"(string-split "abc" "")"#).kind,
+        ErrorKind::InvalidArgument("pattern must not be empty".into())
+    );
+    assert_eq!(
+        err(r#"(number->string "1")"#).kind,
+        ErrorKind::TypeError { expected: "number", found: "string" }
+    );
+}
+
+#[test]
+fn test_equality_of_every_pair_of_kinds() {
+    // One value of each kind, all different from each other
+    let values = [
+        "1", "1.0", "1/2", r#""a""#, r"#\a", "true", "car", "(lambda () 1)",
+        "'(1)", "'()", "'a", "(def 'x 1)",
+    ]
+    .map(|source| eval(source).unwrap());
+    for (i, lhs) in values.iter().enumerate() {
+        for (j, rhs) in values.iter().enumerate() {
+            assert_eq!(lhs == rhs, i == j, "{lhs:?} == {rhs:?}");
+            assert_eq!(lhs.is_eqv(rhs), i == j, "(eqv? {lhs:?} {rhs:?})");
+        }
+    }
+}
+
+#[test]
+fn test_equality_predicates() {
+    assert_eq!(show(r#"(list (= 1 "1") (= '() true) (= 'a 5) (!= 1 "1"))"#), "(false false false true)");
+    assert_eq!(show("(list (= 1 1.0 2/2) (eqv? 1 1.0) (equal? 1 1.0) (equal? 1/2 0.5))"), "(true false false false)");
+    assert_eq!(show("(list (eqv? 2 (+ 1 1)) (eq? 'a 'a) (eqv? #\\a #\\a) (eqv? \"ab\" \"ab\"))"), "(true true true true)");
+    assert_eq!(show("(list (eqv? (/ 0.0 0.0) (/ 0.0 0.0)) (= (/ 0.0 0.0) (/ 0.0 0.0)) (eqv? 0.0 -0.0))"), "(true false false)");
+    assert_eq!(
+        show("(def 'nan (/ 0.0 0.0)) (list (equal? nan nan) (equal? (list nan) (list nan)) (equal? 0.0 -0.0) (= 0.0 -0.0))"),
+        "(true true false true)"
+    );
+
+    // Pairs and functions have an identity, lists are equal? if their elements are
+    assert_eq!(
+        show("(def 'x '(1 (2 3))) (list (eq? x x) (eqv? x '(1 (2 3))) (equal? x '(1 (2 3))) (equal? x '(1 (2 4))))"),
+        "(true false true false)"
+    );
+    assert_eq!(show("(def 'f (lambda () 1)) (list (eq? f f) (eq? car car) (equal? f (lambda () 1)))"), "(true true false)");
+    assert_eq!(show("(list (equal? '(1 . 2) '(1 . 2)) (equal? '(1 2) '(1 . 2)) (eq? '() '()))"), "(true false true)");
+    assert_eq!(err("(equal? 1)").kind, ErrorKind::ArityMismatch { expected: Arity::Exactly(2), got: 1 });
+}
+
+#[test]
+fn test_maps_and_sets() {
+    assert_eq!(show(r#"{"a" (+ 1 2)}"#), "{a 3}");
+    assert_eq!(show("#{(* 2 3)}"), "#{6}");
+    assert_eq!(show("'{a (+ 1 2)}"), "{a (+ 1 2)}");
+    assert_eq!(show("(def 'x 2) `#{1 ,x ,@(list 3 4)}"), show("#{4 3 2 1}"));
+    assert_eq!(eval("(= {1 2 3 4} {3 4 1 2})"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(= #{1 2} #{1 2 3})"), Ok(Value::Bool(false)));
+
+    // Any value can be a key, even another map
+    assert_eq!(show("(get {'(1 2) 'list #{} 'set {1 2} 'map} {1 2})"), "map");
+    assert_eq!(show("(list (get {1 'a} 2) (get {1 'a} 2 'b) (get #{1} 1) (get #{1} 2))"), "(false b 1 false)");
+    assert_eq!(show("(list (contains? {0.0 1} -0.0) (contains? {(/ 0.0 0.0) 1} (/ 0.0 0.0)))"), "(false true)");
+    assert_eq!(show("(def 'nan (/ 0.0 0.0)) (assoc (assoc {} nan 1) nan 2)"), "{NaN 2}");
+    assert_eq!(show("#{(/ 0.0 0.0) (/ 0.0 0.0)}"), "#{NaN}");
+    assert_eq!(show("(list (contains? {1 2} 2) (contains? #{1 2} 2) (contains? {1.0 2} 1))"), "(false true false)");
+
+    // Updates leave the original alone
+    assert_eq!(
+        show("(def 'm {'a 1}) (list (assoc m 'a 2 'b 3) (dissoc m 'a) (update m 'a + 10) m)"),
+        show("(list {'a 2 'b 3} {} {'a 11} {'a 1})"),
+    );
+    assert_eq!(show("(update {} 'n (lambda (old) (if old 1 0)))"), "{n 0}");
+    assert_eq!(show("(dissoc #{1 2 3} 1 3)"), "#{2}");
+    assert_eq!(show("(merge {'a 1 'b 1} {'b 2} {'c 3})"), show("{'a 1 'b 2 'c 3}"));
+    assert_eq!(show("(merge #{1} #{2} #{1 3})"), show("#{1 2 3}"));
+    assert_eq!(show("(def 'm {'a 1 'b 2}) (equal? (map (lambda (k) (get m k)) (keys m)) (vals m))"), "true");
+    assert_eq!(eval("(keys {})"), Ok(Value::Nil));
+
+    assert_eq!(
+        err("(assoc {} 'a)").kind,
+        ErrorKind::ArityMismatch { expected: Arity::AtLeast(3), got: 2 }
+    );
+    assert_eq!(
+        err("(assoc {} 'a 1 'b)").kind,
+        ErrorKind::InvalidArgument("every key needs a value".into())
+    );
+    assert_eq!(
+        err("(merge {} #{})").kind,
+        ErrorKind::TypeError { expected: "map", found: "set" }
+    );
+    assert_eq!(
+        err("`{1 ,@(list 2 3)}").kind,
+        ErrorKind::InvalidSyntax("map literal with a key but no value".into())
+    );
+}
+
+#[test]
+fn test_vectors() {
+    assert_eq!(show("[1 (+ 1 1) 'a \"b\" []]"), "[1 2 a b []]");
+    assert_eq!(show("'[a (b) [c]]"), "[a (b) [c]]");
+    assert_eq!(show("(def 'x 2) `[1 ,x ,@(list 3 4)]"), "[1 2 3 4]");
+    assert_eq!(show("(list (vector-ref [5 6 7] 2) (vector-length [5 6 7]) (vector-length []))"), "(7 3 0)");
+    assert_eq!(show("(vector->list [1 [2]])"), "(1 [2])");
+    assert_eq!(show("(list (make-vector 2) (make-vector 3 'a))"), "([false false] [a a a])");
+    assert_eq!(show("(list (subvector [1 2 3 4] 1 3) (subvector [1 2] 2 2) (subvector [1 2] 0 2))"), "([2 3] [] [1 2])");
+
+    // vector-set! changes the vector in place, everyone holding it sees it
+    assert_eq!(
+        show("(def 'v (make-vector 2 0)) (def 'w v) (vector-set! v 1 'x) (list v w (subvector v 0 1))"),
+        "([0 x] [0 x] [0])"
+    );
+    assert_eq!(show("(def 'v [1]) (list (eq? v v) (eqv? v [1]) (equal? v [1]) (equal? v '(1)))"), "(true false true false)");
+    assert_eq!(show("(get {[1 2] 'found} [1 2])"), "found");
+
+    // Vectors that contain themselves
+    let cycles = "(def 'v [1 2]) (vector-set! v 0 v) (def 'w [1 2]) (vector-set! w 0 w)";
+    assert_eq!(show(&format!("{cycles} (list (equal? v v) (equal? v w) (equal? v [v 2]))")), "(true true true)");
+    assert_eq!(show(&format!("{cycles} (vector-set! w 1 3) (equal? v w)")), "false");
+    assert_eq!(show(&format!("{cycles} (get {{v 'found}} w)")), "found");
+    assert_eq!(show(&format!("{cycles} (list v [v])")), "([#<cycle> 2] [[#<cycle> 2]])");
+    assert_eq!(eval(&format!("{cycles} (vector-set! v 1 (list v)) v")).unwrap().written().to_string(), "[#<cycle> (#<cycle>)]");
+
+    assert_eq!(
+        err("(vector-ref [1 2] 2)").kind,
+        ErrorKind::InvalidArgument("index 2 is out of range".into())
+    );
+    assert_eq!(
+        err("(subvector [1 2 3] 2 1)").kind,
+        ErrorKind::InvalidArgument("index 2 is out of range".into())
+    );
+    assert_eq!(
+        err("(vector-length '(1))").kind,
+        ErrorKind::TypeError { expected: "vector", found: "pair" }
+    );
+}
+
+#[test]
+fn test_mismatched_brackets() {
+    let parse = |source| Parser::new(Lexer::from(source)).parse().unwrap_err().to_string();
+    assert_eq!(parse("(1 2]"), "mismatched closing delimiter at 1:5");
+    assert_eq!(parse("[(1 2])"), "mismatched closing delimiter at 1:6");
+    assert_eq!(parse("{1 2)"), "mismatched closing delimiter at 1:5");
+}
+
+#[test]
+fn test_keywords() {
+    assert_eq!(show(":a"), ":a");
+    assert_eq!(show("(list ':a `(:b ,:c) (car '(:d)))"), "(:a (:b :c) :d)");
+    assert_eq!(show("(def 'm {:a 1 :b 2}) (list (get m :a) (get m :b) (get m :c))"), "(1 2 false)");
+    assert_eq!(show("(list (eq? :a :a) (eq? :a :b) (equal? :a 'a) (= :a \":a\"))"), "(true false false false)");
+    // Keywords hash by name, so this order doesn't change from run to run
+    assert_eq!(show("{:one 1 :two 2 :three 3}"), "{:three 3 :two 2 :one 1}");
+    assert_eq!(show("(defmacro kw () :made) (kw)"), ":made");
+    assert_eq!(show("(define-syntax opt (syntax-rules () ((_ k) (list k :default)))) (opt :x)"), "(:x :default)");
+    assert_eq!(
+        err("(+ :a 1)").kind,
+        ErrorKind::TypeError { expected: "number or string", found: "keyword" }
+    );
+}
+
+#[test]
+fn test_display_and_write() {
+    let write = |source| eval(source).unwrap().written().to_string();
+    assert_eq!(show(r#"(list "a b" #\c 'd :e 1.0 1/2)"#), "(a b c d :e 1.0 1/2)");
+    assert_eq!(write(r#"(list "a b" #\c 'd :e 1.0 1/2)"#), r#"("a b" #\c d :e 1.0 1/2)"#);
+    assert_eq!(write(r#""say \"hi\"\\\n\t\u{1}""#), r#""say \"hi\"\\\n\t\u{1}""#);
+    assert_eq!(write(r"(list #\space #\newline #\x3000 #\( #\λ)"), r"(#\space #\newline #\x3000 #\( #\λ)");
+    assert_eq!(write(r#"[1 "a" {"k" #{#\v}}]"#), r#"[1 "a" {"k" #{#\v}}]"#);
+    assert_eq!(write("(list car (lambda () 1) (cons 1 2))"), "(#<builtin> #<lambda> (1 . 2))");
+    // The reader has no syntax for symbols that aren't identifiers: they are
+    // written bare and don't read back as the same symbol
+    assert_eq!(write(r#"(map string->symbol '("a b" "12" "x"))"#), "(a b 12 x)");
+    assert_eq!(eval(r#"(equal? (string->symbol "12") '12)"#), Ok(Value::Bool(false)));
+
+    // Reading the written form back gives the value again
+    let values = [
+        r#""""#, r#""a\"b\\c\nd\re\tf\0g\u{7}h λ""#, r"#\a", r"#\space", r"#\tab", r"#\nul",
+        r"#\x85", r"#\x3000", r"#\delete", r"#\;", r"#\\", "-12", "3/4", "-0.5", "1e100",
+        "1e-7", "-0.0", "true", "'sym", ":kw", "'()", r#"'(1 ("a" (#\b)) [c])"#,
+        r#"{:a [1 2] "b" #{'c}}"#, "#{}", "{}", "[]",
+    ];
+    for source in values {
+        let value = eval(source).unwrap();
+        let written = value.written().to_string();
+        assert_eq!(eval(&format!("'{written}")), Ok(value), "{source} was written as {written}");
+    }
+
+    for source in [r#"(display "x")"#, r#"(write "x")"#, "(newline)", r#"(print 1 "a" #\b)"#] {
+        assert_eq!(eval(source), Ok(Value::None));
+    }
+    assert_eq!(
+        err("(newline 1)").kind,
+        ErrorKind::ArityMismatch { expected: Arity::Exactly(0), got: 1 }
+    );
+}